@@ -0,0 +1,9 @@
+//! log sites expanded through `omsg!`, compared against `code_size_legacy` by the native code size test
+
+use omsg::omsg;
+
+include!("common/sites.rs");
+
+fn main() {
+    log_sites!(omsg, std::env::args().count() as u64);
+}
@@ -0,0 +1,38 @@
+//! log sites expanded the way `omsg!` used to, with a `format_args!` for each of the six stack
+//! buckets plus a `format!` fallback at every site. kept as the baseline of the native code size test
+
+use omsg::arrform;
+use solana_program::msg;
+
+include!("common/sites.rs");
+
+// sizes the arguments by their memory size, which is what the original macro did
+macro_rules! legacy_sum {
+    ($($args:expr),*) => {{
+        let result = 0;
+        $(
+            #[allow(clippy::size_of_ref)]
+            let result = result + std::mem::size_of_val(&$args);
+        )*
+        result
+    }}
+}
+
+macro_rules! legacy_omsg {
+    ($($args:tt)+) => {
+        let input_sizes = legacy_sum!($($args)*);
+        match input_sizes {
+            s if s <= 768 && s > 512 => msg!("{}", arrform!(768, $($args)*).as_str()),
+            s if s <= 512 && s > 256 => msg!("{}", arrform!(512, $($args)*).as_str()),
+            s if s <= 256 && s > 128 => msg!("{}", arrform!(256, $($args)*).as_str()),
+            s if s <= 128 && s > 64 => msg!("{}", arrform!(128, $($args)*).as_str()),
+            s if s <= 64 && s > 32 => msg!("{}", arrform!(64, $($args)*).as_str()),
+            s if s <= 32 && s > 0 => msg!("{}", arrform!(32, $($args)*).as_str()),
+            _ => msg!("{}", format!($($args)*)),
+        }
+    };
+}
+
+fn main() {
+    log_sites!(legacy_omsg, std::env::args().count() as u64);
+}
@@ -0,0 +1,35 @@
+/// logs a representative mix of messages through the `$log` macro eight times over, giving
+/// 128 separate log sites so that the code each expansion emits outweighs the fixed cost of the
+/// functions shared between the sites
+macro_rules! log_sites {
+    ($log:ident, $n:expr) => {{
+        let n: u64 = $n;
+        log_sites!(@sites $log, n);
+        log_sites!(@sites $log, n + 1);
+        log_sites!(@sites $log, n + 2);
+        log_sites!(@sites $log, n + 3);
+        log_sites!(@sites $log, n + 4);
+        log_sites!(@sites $log, n + 5);
+        log_sites!(@sites $log, n + 6);
+        log_sites!(@sites $log, n + 7);
+    }};
+    (@sites $log:ident, $n:expr) => {{
+        let n: u64 = $n;
+        $log!("refreshing reserve {}", n);
+        $log!("deposited {} into reserve {}", n, n as u8);
+        $log!("withdrew {} from obligation {}", n * 2, n + 1);
+        $log!("borrow rate {} utilization {}", n as u16, n as u32);
+        $log!("liquidity {} collateral {} debt {}", n, n + 3, n * 7);
+        $log!("obligation {} is healthy: {}", n, n % 2 == 0);
+        $log!("market price {} slot {}", n as i64 - 5, n + 100);
+        $log!("harvested {} rewards for vault {}", n * 3, n as u8);
+        $log!("compounded {} shares at index {}", n + 11, n as usize);
+        $log!("rebalanced {} of {} tokens", n / 2, n);
+        $log!("fee {} bps on {} lamports", n as u16 % 10_000, n);
+        $log!("position {} closed with pnl {}", n, n as i128 - 42);
+        $log!("crank {} processed {} accounts", n + 1, n as u8 + 1);
+        $log!("user {} staked {} until {}", n, n * 5, n + 86_400);
+        $log!("oracle {} confidence {} expo {}", n, n / 3, n as i32 - 8);
+        $log!("settled {} orders totalling {}", n as u32, n * 1_000);
+    }};
+}
@@ -0,0 +1,268 @@
+//! procedural macros of omsg. the format string of `omsg_fast!` and `oformat!` is parsed at
+//! compile time and expanded into direct calls of `omsg::fast::OmsgWrite::push_str` for the
+//! literal text and `omsg::fast::OmsgDisplay::omsg_fmt` for every placeholder, so the
+//! expansion doesn't touch `core::fmt`. the macros are invoked through the `macro_rules!` front
+//! ends of the omsg crate, which pass `$crate` in brackets ahead of the arguments so that the
+//! expansion refers to the omsg crate by the path it was given, even if the dependency was renamed.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Error, Expr, Ident, LitStr, Token};
+
+/// logs a message formatted without `core::fmt`, see `omsg::omsg_fast!`
+#[proc_macro]
+pub fn __omsg_fast(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as Rooted<FormatArgs>);
+    expand(&input.root, input.args, None).unwrap_or_else(Error::into_compile_error).into()
+}
+
+/// formats a message into an `ArrForm` without `core::fmt`, see `omsg::oformat!`
+#[proc_macro]
+pub fn __oformat(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as Rooted<SizedFormatArgs>);
+    expand(&input.root, input.args.args, Some(input.args.size)).unwrap_or_else(Error::into_compile_error).into()
+}
+
+/// the arguments of a macro, preceded by the path of the omsg crate in brackets
+struct Rooted<T> {
+    root: TokenStream2,
+    args: T,
+}
+
+/// the format string and arguments of a macro invocation
+struct FormatArgs {
+    fmt: LitStr,
+    positional: Vec<Expr>,
+    named: Vec<(Ident, Expr)>,
+}
+
+/// the buffer size followed by the format string and arguments of `oformat!`
+struct SizedFormatArgs {
+    size: Expr,
+    args: FormatArgs,
+}
+
+impl Parse for FormatArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let fmt: LitStr = input.parse()?;
+        let mut positional = Vec::new();
+        let mut named = Vec::new();
+        if input.parse::<Option<Token![,]>>()?.is_some() {
+            for arg in Punctuated::<Expr, Token![,]>::parse_terminated(input)? {
+                match arg {
+                    Expr::Assign(assign) => {
+                        let name = match *assign.left {
+                            Expr::Path(path) if path.path.get_ident().is_some() => path.path.get_ident().cloned().unwrap(),
+                            left => return Err(Error::new_spanned(left, "expected an argument name")),
+                        };
+                        if named.iter().any(|(existing, _)| *existing == name) {
+                            return Err(Error::new_spanned(name, "duplicate argument name"));
+                        }
+                        named.push((name, *assign.right));
+                    }
+                    arg if named.is_empty() => positional.push(arg),
+                    arg => return Err(Error::new_spanned(arg, "positional arguments must come before named arguments")),
+                }
+            }
+        }
+        Ok(FormatArgs { fmt, positional, named })
+    }
+}
+
+impl<T: Parse> Parse for Rooted<T> {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let root;
+        syn::bracketed!(root in input);
+        let root = root.parse()?;
+        let args = input.parse()?;
+        Ok(Rooted { root, args })
+    }
+}
+
+impl Parse for SizedFormatArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let size = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let args = input.parse()?;
+        Ok(SizedFormatArgs { size, args })
+    }
+}
+
+/// a part of a parsed format string
+enum Piece {
+    /// literal text with its escapes resolved
+    Text(String),
+    /// a placeholder, referring to the argument with the given index
+    Arg(usize),
+}
+
+/// the arguments of a format string, in the order they are evaluated
+struct Args {
+    exprs: Vec<Expr>,
+    names: Vec<Option<Ident>>,
+    used: Vec<bool>,
+}
+
+impl Args {
+    fn new(input: &FormatArgs) -> Self {
+        let mut exprs = input.positional.clone();
+        let mut names = vec![None; exprs.len()];
+        for (name, expr) in &input.named {
+            exprs.push(expr.clone());
+            names.push(Some(name.clone()));
+        }
+        let used = vec![false; exprs.len()];
+        Args { exprs, names, used }
+    }
+
+    /// returns the index of the argument `name`, capturing a variable of that name if no
+    /// such argument was given
+    fn named(&mut self, name: &str, span: Span) -> usize {
+        let index = match self.names.iter().position(|existing| existing.as_ref().is_some_and(|n| n == name)) {
+            Some(index) => index,
+            None => {
+                let ident = Ident::new(name, span);
+                self.exprs.push(syn::parse_quote!(#ident));
+                self.names.push(Some(ident));
+                self.used.push(false);
+                self.exprs.len() - 1
+            }
+        };
+        self.used[index] = true;
+        index
+    }
+}
+
+/// parses `fmt` into pieces, marking every argument it refers to as used
+fn parse_format(fmt: &LitStr, positional: usize, args: &mut Args) -> syn::Result<Vec<Piece>> {
+    let value = fmt.value();
+    let error = |message: &str| Error::new(fmt.span(), message);
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut next_positional = 0;
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '}' => return Err(error("unmatched `}` in format string, escape it as `}}`")),
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err(error("unterminated `{` in format string, escape it as `{{`")),
+                    }
+                }
+                let positional_index = |index: usize| {
+                    if index < positional {
+                        Ok(index)
+                    } else {
+                        Err(error(&format!(
+                            "placeholder refers to argument {} but {} positional arguments were given",
+                            index, positional
+                        )))
+                    }
+                };
+                let index = if placeholder.contains(':') {
+                    return Err(error("format specs are not supported, use `omsg!` for them"));
+                } else if placeholder.is_empty() {
+                    next_positional += 1;
+                    positional_index(next_positional - 1)?
+                } else if let Ok(index) = placeholder.parse::<usize>() {
+                    positional_index(index)?
+                } else if syn::parse_str::<Ident>(&placeholder).is_ok() {
+                    args.named(&placeholder, fmt.span())
+                } else {
+                    return Err(error(&format!("invalid placeholder `{{{}}}`", placeholder)));
+                };
+                args.used[index] = true;
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(Piece::Arg(index));
+            }
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+/// expands `omsg_fast!` when `size` is `None`, or `oformat!` into a buffer of `size` bytes.
+/// `root` is the path of the omsg crate
+fn expand(root: &TokenStream2, input: FormatArgs, size: Option<Expr>) -> syn::Result<TokenStream2> {
+    let mut args = Args::new(&input);
+    let pieces = parse_format(&input.fmt, input.positional.len(), &mut args)?;
+    if let Some(unused) = args.used.iter().position(|used| !used) {
+        return Err(Error::new_spanned(&args.exprs[unused], "argument never used"));
+    }
+
+    // the temporaries of the expansion can't collide with the names of captured variables
+    let writer = Ident::new("w", Span::mixed_site());
+    let binders: Vec<Ident> = (0..args.exprs.len())
+        .map(|i| format_ident!("arg{}", i, span = Span::mixed_site()))
+        .collect();
+    let exprs = &args.exprs;
+    let pushes = pieces.iter().map(|piece| match piece {
+        Piece::Text(text) => quote! { #root::fast::OmsgWrite::push_str(#writer, #text); },
+        Piece::Arg(index) => {
+            let binder = &binders[*index];
+            quote! { #root::fast::OmsgDisplay::omsg_fmt(#binder, #writer); }
+        }
+    });
+
+    if let Some(size) = size {
+        let af = Ident::new("af", Span::mixed_site());
+        return Ok(quote! {{
+            let mut #af = #root::ArrForm::<{ #size }>::new();
+            match (#(&#exprs,)*) {
+                (#(#binders,)*) => {
+                    let #writer = &mut #af;
+                    #(#pushes)*
+                }
+            }
+            #af
+        }});
+    }
+
+    // a message without placeholders is logged as is
+    if binders.is_empty() {
+        let text = match pieces.as_slice() {
+            [Piece::Text(text)] => text.as_str(),
+            _ => "",
+        };
+        return Ok(quote! { #root::buckets::log_literal(#text) });
+    }
+    let text_len: usize = pieces
+        .iter()
+        .map(|piece| match piece {
+            Piece::Text(text) => text.len(),
+            Piece::Arg(_) => 0,
+        })
+        .sum();
+    Ok(quote! {
+        match (#(&#exprs,)*) {
+            (#(#binders,)*) => {
+                #[allow(unused_imports)]
+                use #root::sizing::{KnownSize as _, UnknownSize as _};
+                #root::buckets::log_pieces(
+                    #text_len #(.saturating_add((&#root::sizing::SizeOf(&#binders)).omsg_size()))*,
+                    &mut |#writer: &mut dyn #root::fast::OmsgWrite| { #(#pushes)* },
+                )
+            }
+        }
+    })
+}
@@ -0,0 +1,229 @@
+//! fixed point display of token amounts. an amount is stored on chain as an integer number of
+//! base units together with the number of decimals of its mint, so `1234567` with 6 decimals
+//! is `1.234567` tokens. [TokenAmount] displays it that way using integer arithmetic only,
+//! instead of converting to `f64` and formatting with `{:.6}`, which is emulated in software
+//! on chain and is the most expensive kind of formatting there is.
+//!
+//! ```
+//! use omsg::amount::{DecimalStyle, TokenAmount};
+//! use omsg::ArrForm;
+//!
+//! let amount = TokenAmount::new(1_230_000, 6);
+//! let mut af = ArrForm::<64>::new();
+//! af.push_token_amount(amount, DecimalStyle::Full).unwrap();
+//! af.push_str(" ").unwrap();
+//! af.push_token_amount(amount, DecimalStyle::TrimZeros).unwrap();
+//! af.push_str(" ").unwrap();
+//! af.push_token_amount(amount, DecimalStyle::Fixed(1)).unwrap();
+//! assert_eq!(af, "1.230000 1.23 1.2");
+//!
+//! // the styles are also available as wrappers for `omsg!` and `omsg_fast!`
+//! assert_eq!(amount.trim_zeros().to_string(), "1.23");
+//! ```
+
+use crate::fast::{OmsgDisplay, OmsgWrite};
+use crate::sizing::MaxDisplayLen;
+use crate::ArrForm;
+use core::fmt;
+
+/// the length of the longest display of an amount: the 20 digits of `u64::MAX`, the decimal
+/// point and 255 decimals
+const MAX_LEN: usize = 20 + 1 + u8::MAX as usize;
+
+/// an amount of base units of a token with `decimals` decimals, displayed with all of its
+/// decimals
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TokenAmount {
+    /// the amount in base units, as stored in token accounts
+    pub raw: u64,
+    /// the decimals of the mint
+    pub decimals: u8,
+}
+
+/// how many decimals of a [TokenAmount] are displayed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DecimalStyle {
+    /// every decimal of the mint, `1.230000`
+    #[default]
+    Full,
+    /// without trailing zeros, and without the decimal point for whole amounts, `1.23`
+    TrimZeros,
+    /// exactly this many decimals. extra decimals are cut off rather than rounded, so an
+    /// amount is never displayed as more than it is
+    Fixed(u8),
+}
+
+/// a [TokenAmount] displayed in a [DecimalStyle], see [TokenAmount::trim_zeros] and
+/// [TokenAmount::fixed]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FormattedAmount {
+    pub amount: TokenAmount,
+    pub style: DecimalStyle,
+}
+
+impl TokenAmount {
+    pub const fn new(raw: u64, decimals: u8) -> Self {
+        TokenAmount { raw, decimals }
+    }
+
+    /// displays the amount without trailing zeros
+    pub const fn trim_zeros(self) -> FormattedAmount {
+        FormattedAmount { amount: self, style: DecimalStyle::TrimZeros }
+    }
+
+    /// displays the amount with exactly `decimals` decimals
+    pub const fn fixed(self, decimals: u8) -> FormattedAmount {
+        FormattedAmount { amount: self, style: DecimalStyle::Fixed(decimals) }
+    }
+
+    /// the maximum length of the amount displayed in `style`
+    const fn max_len(self, style: DecimalStyle) -> usize {
+        let decimals = match style {
+            DecimalStyle::Full | DecimalStyle::TrimZeros => self.decimals,
+            DecimalStyle::Fixed(decimals) => decimals,
+        };
+        if decimals == 0 {
+            20
+        } else {
+            20 + 1 + decimals as usize
+        }
+    }
+
+    /// writes the amount displayed in `style` into `buf`
+    fn render(self, style: DecimalStyle, buf: &mut [u8; MAX_LEN]) -> &str {
+        let mut digits = [0u8; 20];
+        let digits = crate::num::u64_digits(self.raw, &mut digits).as_bytes();
+        let decimals = self.decimals as usize;
+        // the fraction consists of `leading_zeros` zeros followed by `fraction`
+        let split = digits.len().saturating_sub(decimals);
+        let (whole, fraction) = digits.split_at(split);
+        let leading_zeros = decimals - fraction.len();
+        let shown = match style {
+            DecimalStyle::Full => decimals,
+            DecimalStyle::TrimZeros => match fraction.iter().rposition(|&digit| digit != b'0') {
+                Some(last) => leading_zeros + last + 1,
+                None => 0,
+            },
+            DecimalStyle::Fixed(shown) => shown as usize,
+        };
+
+        let mut len = 0;
+        let mut push = |bytes: &[u8]| {
+            buf[len..len + bytes.len()].copy_from_slice(bytes);
+            len += bytes.len();
+        };
+        push(if whole.is_empty() { b"0" } else { whole });
+        if shown > 0 {
+            push(b".");
+            for _ in 0..shown.min(leading_zeros) {
+                push(b"0");
+            }
+            push(&fraction[..shown.saturating_sub(leading_zeros).min(fraction.len())]);
+            for _ in decimals..shown {
+                push(b"0");
+            }
+        }
+        // only ascii digits and the decimal point were written
+        unsafe { core::str::from_utf8_unchecked(&buf[..len]) }
+    }
+}
+
+impl<const BUF_SIZE: usize> ArrForm<BUF_SIZE> {
+    /// appends `amount` with its decimals displayed in `style`
+    pub fn push_token_amount(&mut self, amount: TokenAmount, style: DecimalStyle) -> fmt::Result {
+        self.push_str(amount.render(style, &mut [0; MAX_LEN]))
+    }
+}
+
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.render(DecimalStyle::Full, &mut [0; MAX_LEN]))
+    }
+}
+
+impl fmt::Display for FormattedAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.amount.render(self.style, &mut [0; MAX_LEN]))
+    }
+}
+
+impl OmsgDisplay for TokenAmount {
+    fn omsg_fmt<W: OmsgWrite + ?Sized>(&self, w: &mut W) {
+        w.push_str(self.render(DecimalStyle::Full, &mut [0; MAX_LEN]))
+    }
+}
+
+impl OmsgDisplay for FormattedAmount {
+    fn omsg_fmt<W: OmsgWrite + ?Sized>(&self, w: &mut W) {
+        w.push_str(self.amount.render(self.style, &mut [0; MAX_LEN]))
+    }
+}
+
+impl MaxDisplayLen for TokenAmount {
+    fn max_display_len(&self) -> usize {
+        self.max_len(DecimalStyle::Full)
+    }
+}
+
+impl MaxDisplayLen for FormattedAmount {
+    fn max_display_len(&self) -> usize {
+        self.amount.max_len(self.style)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(raw: u64, decimals: u8, style: DecimalStyle) -> String {
+        TokenAmount::new(raw, decimals).render(style, &mut [0; MAX_LEN]).to_string()
+    }
+
+    #[test]
+    fn test_full() {
+        assert_eq!(render(1_234_567, 6, DecimalStyle::Full), "1.234567");
+        assert_eq!(render(1_234_567, 0, DecimalStyle::Full), "1234567");
+        assert_eq!(render(42, 6, DecimalStyle::Full), "0.000042");
+        assert_eq!(render(0, 9, DecimalStyle::Full), "0.000000000");
+        assert_eq!(render(u64::MAX, 25, DecimalStyle::Full), format!("0.00000{}", u64::MAX));
+    }
+
+    #[test]
+    fn test_trim_zeros() {
+        assert_eq!(render(1_230_000, 6, DecimalStyle::TrimZeros), "1.23");
+        assert_eq!(render(5_000_000, 6, DecimalStyle::TrimZeros), "5");
+        assert_eq!(render(0, 6, DecimalStyle::TrimZeros), "0");
+        assert_eq!(render(1_000_100, 9, DecimalStyle::TrimZeros), "0.0010001");
+        assert_eq!(render(1200, 0, DecimalStyle::TrimZeros), "1200");
+    }
+
+    #[test]
+    fn test_fixed() {
+        assert_eq!(render(1_239_999, 6, DecimalStyle::Fixed(2)), "1.23");
+        assert_eq!(render(1_234_567, 6, DecimalStyle::Fixed(0)), "1");
+        assert_eq!(render(15, 1, DecimalStyle::Fixed(4)), "1.5000");
+        assert_eq!(render(42, 6, DecimalStyle::Fixed(5)), "0.00004");
+        assert_eq!(render(42, 6, DecimalStyle::Fixed(3)), "0.000");
+        assert_eq!(render(7, 0, DecimalStyle::Fixed(2)), "7.00");
+    }
+
+    #[test]
+    fn test_max_display_len() {
+        for style in [DecimalStyle::Full, DecimalStyle::TrimZeros, DecimalStyle::Fixed(3), DecimalStyle::Fixed(0)] {
+            for (raw, decimals) in [(u64::MAX, 0), (u64::MAX, 6), (1, 30), (0, 0)] {
+                let amount = FormattedAmount { amount: TokenAmount::new(raw, decimals), style };
+                assert!(amount.to_string().len() <= amount.max_display_len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_wrappers() {
+        let amount = TokenAmount::new(2_500_000_000, 9);
+        assert_eq!(format!("{:>8}", amount.fixed(2)), "    2.50");
+        let mut text = String::new();
+        amount.trim_zeros().omsg_fmt(&mut text);
+        assert_eq!(text, "2.5");
+        assert_eq!(amount.to_string(), "2.500000000");
+    }
+}
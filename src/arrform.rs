@@ -1,7 +1,4 @@
-#![no_std]
-
-
-//! for licensing information see https://github.com/Simsys/arrform
+//! for licensing information see <https://github.com/Simsys/arrform>
 //! due to sensitive nature of solana programs, and the small size of arrform
 //! it has been incldued
 //! 
@@ -13,13 +10,15 @@
 //! environments because they require a memory allocator. The arrform! macro uses the standard 
 //! library functions, but writes to a fixed length array which is alocated on the stack.
 //! 
-//! This crate is usable in no_std environments. This is a replacement for the format! macro, based 
-//! on a fixed-size array allocated on the stack.
+//! Formatting into an [ArrForm] never allocates, but unlike the original arrform crate this 
+//! module is not `no_std`: omsg depends on `std` through solana-program, and [ArrFormError] 
+//! implements `std::error::Error`. This is a replacement for the format! macro, based on a 
+//! fixed-size array allocated on the stack.
 //! 
 //! # arrform!
 //! 
 //! ``` rust
-//! use arrform::{arrform, ArrForm};
+//! use omsg::arrform;
 //! 
 //! let af = arrform!(64, "write some stuff {}: {:.2}", "foo", 42.3456);
 //! assert_eq!("write some stuff foo: 42.35", af.as_str());
@@ -42,7 +41,10 @@
 //! 
 //! Apache version 2.0 or Mit
 //!
-use core::{fmt, str::from_utf8_unchecked};
+use core::{fmt, ptr, slice, str::from_utf8_unchecked};
+use core::borrow::Borrow;
+use core::hash::{Hash, Hasher};
+use core::ops::Deref;
 use core::mem::MaybeUninit;
 
 #[allow(unused_imports)]
@@ -53,7 +55,7 @@ use core::format_args;
 /// Allows precise handling of errors. A buffer created once can be used several times. The 
 /// application requires more typing and contains some syntactic noise.
 /// ```
-/// use arrform::ArrForm;
+/// use omsg::ArrForm;
 /// 
 /// let mut af = ArrForm::<64>::new();
 /// match af.format(format_args!("write some stuff {}: {:.2}", "foo", 42.3456)) {
@@ -74,56 +76,352 @@ use core::format_args;
 /// assert_eq!("same buffer, new text, int 123, float 4.1", af.as_str());
 /// ```
 pub struct ArrForm<const BUF_SIZE: usize> {
-    buffer: [u8; BUF_SIZE],
+    // Only the first `used` bytes are initialized
+    buffer: [MaybeUninit<u8>; BUF_SIZE],
     used: usize,
+    truncated: bool,
+    policy: OverflowPolicy,
+}
+
+/// What an [ArrForm] does when the text doesn't fit into its buffer
+/// 
+/// Whatever the policy, the buffer only ever holds whole utf8 characters.
+/// ```
+/// use omsg::{ArrForm, OverflowPolicy};
+/// 
+/// let mut af = ArrForm::<16>::with_policy(OverflowPolicy::TruncateWithEllipsis);
+/// af.format(format_args!("liquidating obligation {}", 42)).unwrap();
+/// assert_eq!("liquidating o…", af.as_str());
+/// assert!(af.is_truncated());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum OverflowPolicy {
+    /// Panic, which is what [arrform!](crate::arrform!) does
+    Panic,
+    /// Keep the text that fits and return an error, the default
+    #[default]
+    Error,
+    /// Keep the text that fits and silently drop the rest
+    Truncate,
+    /// Like `Truncate`, but end the text with a "…" marker. Buffers smaller than the 3 bytes of 
+    /// the marker are truncated without it
+    TruncateWithEllipsis,
+}
+
+const ELLIPSIS: &str = "…";
+
+/// Error returned when formatting into an [ArrForm] fails
+/// 
+/// Reports how many bytes were written before formatting stopped and the capacity of the 
+/// buffer. The bytes written are still available through the [ArrForm], so the truncated 
+/// text can be used instead of the full message.
+/// ```
+/// use omsg::{try_arrform, ArrFormError};
+/// 
+/// let err = try_arrform!(8, "liquidity {}", 1234567).unwrap_err();
+/// assert_eq!(err, ArrFormError { written: 8, capacity: 8, truncated: true });
+/// assert_eq!("output truncated after 8 of 8 bytes", err.to_string());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArrFormError {
+    /// Number of bytes written to the buffer before formatting stopped
+    pub written: usize,
+    /// Size of the buffer
+    pub capacity: usize,
+    /// True if formatting stopped because the buffer was full, false if a formatting trait 
+    /// implementation returned an error
+    pub truncated: bool,
 }
 
+impl fmt::Display for ArrFormError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.truncated {
+            write!(f, "output truncated after {} of {} bytes", self.written, self.capacity)
+        } else {
+            write!(f, "formatting failed after {} of {} bytes", self.written, self.capacity)
+        }
+    }
+}
+
+impl std::error::Error for ArrFormError {}
+
 impl<const BUF_SIZE: usize> ArrForm<BUF_SIZE> {
 
     /// Creates new buffer on the stack
     pub fn new() -> Self {
-        // We don't need to initialize, because we write before we read
-        let buffer: [u8; BUF_SIZE] = unsafe { MaybeUninit::uninit().assume_init() };
-        ArrForm { buffer, used: 0 }
+        Self::with_policy(OverflowPolicy::Error)
+    }
+
+    /// Creates new buffer on the stack which handles overflows according to `policy`
+    pub fn with_policy(policy: OverflowPolicy) -> Self {
+        // We don't need to initialize, because we write before we read. An array of 
+        // `MaybeUninit` doesn't require initialization, so this is sound.
+        let buffer: [MaybeUninit<u8>; BUF_SIZE] = unsafe { MaybeUninit::uninit().assume_init() };
+        ArrForm { buffer, used: 0, truncated: false, policy }
+    }
+
+    /// Get the overflow policy of the buffer
+    pub fn policy(&self) -> OverflowPolicy {
+        self.policy
+    }
+
+    /// Change the overflow policy of the buffer
+    pub fn set_policy(&mut self, policy: OverflowPolicy) {
+        self.policy = policy;
     }
 
     /// Format numbers and strings
     pub fn format(&mut self, args: fmt::Arguments) -> fmt::Result {
         self.used = 0;                  // if format is used several times
+        self.truncated = false;
         fmt::write(self, args)
     }
 
+    /// Append formatted numbers and strings to the text already in the buffer
+    /// 
+    /// Unlike [ArrForm::format] the buffer is not reset, so a message can be built in pieces.
+    /// ```
+    /// use omsg::ArrForm;
+    /// 
+    /// let mut af = ArrForm::<64>::new();
+    /// af.push_str("obligations:").unwrap();
+    /// for obligation in [3, 7] {
+    ///     af.append(format_args!(" {}", obligation)).unwrap();
+    /// }
+    /// af.push_char('.').unwrap();
+    /// assert_eq!("obligations: 3 7.", af.as_str());
+    /// ```
+    pub fn append(&mut self, args: fmt::Arguments) -> fmt::Result {
+        fmt::write(self, args)
+    }
+
+    /// Append a string to the text already in the buffer
+    pub fn push_str(&mut self, s: &str) -> fmt::Result {
+        fmt::Write::write_str(self, s)
+    }
+
+    /// Append a character to the text already in the buffer
+    pub fn push_char(&mut self, c: char) -> fmt::Result {
+        self.push_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Format numbers and strings, reporting how much was written if formatting fails
+    /// 
+    /// The text written up to the failure remains in the buffer. With one of the truncating 
+    /// policies an overflow is not an error, use [ArrForm::is_truncated] to detect it.
+    pub fn try_format(&mut self, args: fmt::Arguments) -> Result<(), ArrFormError> {
+        self.format(args).map_err(|_| ArrFormError {
+            written: self.used,
+            capacity: BUF_SIZE,
+            truncated: self.truncated,
+        })
+    }
+
+    /// Returns true if the text in the buffer was cut off because the buffer was full
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Length of the text in the buffer in bytes
+    pub fn len(&self) -> usize {
+        self.used
+    }
+
+    /// Returns true if the buffer holds no text
+    pub fn is_empty(&self) -> bool {
+        self.used == 0
+    }
+
+    /// Size of the buffer in bytes
+    pub const fn capacity(&self) -> usize {
+        BUF_SIZE
+    }
+
+    /// Number of bytes that can still be written to the buffer
+    pub fn remaining(&self) -> usize {
+        BUF_SIZE - self.used
+    }
+
+    /// Removes all text from the buffer, keeping its overflow policy
+    pub fn clear(&mut self) {
+        self.used = 0;
+        self.truncated = false;
+    }
+
+    /// Shortens the text to `new_len` bytes, does nothing if the text is already shorter
+    /// 
+    /// Panics if `new_len` does not lie on a char boundary, like `String::truncate`.
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len < self.used {
+            assert!(self.as_str().is_char_boundary(new_len), "new_len must lie on a char boundary");
+            self.used = new_len;
+            self.truncated = false;
+        }
+    }
+
     /// Get a reference to the result as a slice inside the buffer as str
     pub fn as_str(&self) -> &str {
-        // We are really sure, that the buffer contains only valid utf8 characters
-        unsafe { from_utf8_unchecked(&self.buffer[..self.used]) }
+        // We are really sure, that the buffer contains only valid utf8 characters, as only 
+        // whole characters are ever written
+        unsafe { from_utf8_unchecked(self.as_bytes()) }
     }
 
     /// Get a reference to the result as a slice inside the buffer as bytes
     pub fn as_bytes(&self) -> &[u8] {
-        &self.buffer[..self.used]
+        // The first `used` bytes have been initialized by `write_bytes`
+        unsafe { slice::from_raw_parts(self.buffer.as_ptr().cast::<u8>(), self.used) }
+    }
+
+    /// Appends `bytes` behind the initialized part of the buffer, panics if they don't fit
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        let dst = &mut self.buffer[self.used..self.used + bytes.len()];
+        // The bounds of `dst` are checked above, and `MaybeUninit<u8>` has the layout of `u8`
+        unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), dst.as_mut_ptr().cast::<u8>(), bytes.len()) };
+        self.used += bytes.len();
+    }
+}
+
+impl<const BUF_SIZE: usize> Default for ArrForm<BUF_SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const BUF_SIZE: usize> Clone for ArrForm<BUF_SIZE> {
+    fn clone(&self) -> Self {
+        // Only the initialized prefix is copied
+        let mut af = Self::with_policy(self.policy);
+        af.write_bytes(self.as_bytes());
+        af.truncated = self.truncated;
+        af
+    }
+}
+
+impl<const BUF_SIZE: usize> fmt::Display for ArrForm<BUF_SIZE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl<const BUF_SIZE: usize> fmt::Debug for ArrForm<BUF_SIZE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl<const BUF_SIZE: usize> Deref for ArrForm<BUF_SIZE> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const BUF_SIZE: usize> AsRef<str> for ArrForm<BUF_SIZE> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const BUF_SIZE: usize> AsRef<[u8]> for ArrForm<BUF_SIZE> {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<const BUF_SIZE: usize> Borrow<str> for ArrForm<BUF_SIZE> {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+// Equality and hashing only consider the text, consistent with `Borrow<str>`
+impl<const BUF_SIZE: usize, const OTHER_SIZE: usize> PartialEq<ArrForm<OTHER_SIZE>> for ArrForm<BUF_SIZE> {
+    fn eq(&self, other: &ArrForm<OTHER_SIZE>) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl<const BUF_SIZE: usize> Eq for ArrForm<BUF_SIZE> {}
+
+impl<const BUF_SIZE: usize> PartialEq<str> for ArrForm<BUF_SIZE> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<const BUF_SIZE: usize> PartialEq<&str> for ArrForm<BUF_SIZE> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<const BUF_SIZE: usize> PartialEq<ArrForm<BUF_SIZE>> for str {
+    fn eq(&self, other: &ArrForm<BUF_SIZE>) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl<const BUF_SIZE: usize> PartialEq<ArrForm<BUF_SIZE>> for &str {
+    fn eq(&self, other: &ArrForm<BUF_SIZE>) -> bool {
+        *self == other.as_str()
+    }
+}
+
+impl<const BUF_SIZE: usize> Hash for ArrForm<BUF_SIZE> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
     }
 }
 
 impl<const BUF_SIZE: usize> fmt::Write for ArrForm<BUF_SIZE> {
 
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        let remaining_buf = &mut self.buffer[self.used..];
+        // Once truncated, nothing is appended so that the text stays a prefix of the message
+        if self.truncated {
+            return self.overflow();
+        }
+        let remaining = BUF_SIZE - self.used;
         let raw_s = s.as_bytes();
 
         // Treat imminent buffer overflow
-        if raw_s.len() > remaining_buf.len() {
-            remaining_buf.copy_from_slice(&raw_s[..remaining_buf.len()]);
-            self.used += remaining_buf.len();
-            Err(fmt::Error)
+        if raw_s.len() > remaining {
+            // Only copy whole characters, so the buffer always holds valid utf8
+            let mut fits = remaining;
+            while !s.is_char_boundary(fits) {
+                fits -= 1;
+            }
+            self.write_bytes(&raw_s[..fits]);
+            self.truncated = true;
+            if self.policy == OverflowPolicy::TruncateWithEllipsis && BUF_SIZE >= ELLIPSIS.len() {
+                // Drop whole characters until the marker fits
+                let as_str = self.as_str();
+                let mut keep = BUF_SIZE - ELLIPSIS.len();
+                while !as_str.is_char_boundary(keep) {
+                    keep -= 1;
+                }
+                self.used = keep.min(self.used);
+                self.write_bytes(ELLIPSIS.as_bytes());
+            }
+            self.overflow()
         } else {
-            remaining_buf[..raw_s.len()].copy_from_slice(raw_s);
-            self.used += raw_s.len();
+            self.write_bytes(raw_s);
             Ok(())
         }
     }
 }
 
+impl<const BUF_SIZE: usize> ArrForm<BUF_SIZE> {
+    /// The result of writing to a truncated buffer, according to the policy
+    fn overflow(&self) -> fmt::Result {
+        match self.policy {
+            OverflowPolicy::Panic => panic!("Buffer overflow"),
+            OverflowPolicy::Error => Err(fmt::Error),
+            OverflowPolicy::Truncate | OverflowPolicy::TruncateWithEllipsis => Ok(()),
+        }
+    }
+}
+
 /// A macro to format numers into text, based on a fixed-size array allocated on the stack
 /// 
 /// This macro first reserves a buffer on the stack. Then it uses the struct [ArrForm] to format 
@@ -131,7 +429,7 @@ impl<const BUF_SIZE: usize> fmt::Write for ArrForm<BUF_SIZE> {
 /// text. The macro panics if the buffer is chosen too small.
 /// 
 /// ```
-/// use arrform::{arrform, ArrForm};
+/// use omsg::arrform;
 /// 
 /// let af = arrform!(64, "write some {}, int {}, float {:.3}", "stuff", 4711, 3.1415);
 /// assert_eq!("write some stuff, int 4711, float 3.142", af.as_str());
@@ -139,10 +437,298 @@ impl<const BUF_SIZE: usize> fmt::Write for ArrForm<BUF_SIZE> {
 #[macro_export]
 macro_rules! arrform {
     ($size:expr, $($arg:tt)*) => {{
-        let mut af = ArrForm::<$size>::new();
+        let mut af = $crate::ArrForm::<$size>::new();
 
         // Panic on buffer overflow
-        af.format(format_args!($($arg)*)).expect("Buffer overflow");
+        af.format(::core::format_args!($($arg)*)).expect("Buffer overflow");
         af
     }}
 }
+
+/// A non panicking variant of [arrform!](crate::arrform!)
+/// 
+/// Returns the [ArrForm] if the text fits into the buffer, or an [ArrFormError] describing 
+/// how much was written otherwise.
+/// 
+/// ```
+/// use omsg::try_arrform;
+/// 
+/// let af = try_arrform!(64, "write some {}, int {}", "stuff", 4711).unwrap();
+/// assert_eq!("write some stuff, int 4711", af.as_str());
+/// assert!(try_arrform!(16, "write some {}, int {}", "stuff", 4711).is_err());
+/// ```
+#[macro_export]
+macro_rules! try_arrform {
+    ($size:expr, $($arg:tt)*) => {{
+        let mut af = $crate::ArrForm::<$size>::new();
+        af.try_format(::core::format_args!($($arg)*)).map(|()| af)
+    }}
+}
+
+// The tests don't read uninitialized memory, so they also run under Miri in the `miri` job of 
+// CI: `cargo +nightly miri test --lib arrform`
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::fmt::Write;
+    use proptest::prelude::*;
+
+    struct Failing;
+
+    impl fmt::Display for Failing {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("partial")?;
+            Err(fmt::Error)
+        }
+    }
+
+    #[test]
+    fn test_try_format_overflow() {
+        let mut af = ArrForm::<16>::new();
+        let err = af.try_format(format_args!("{} {}", "liquidity", u64::MAX)).unwrap_err();
+        assert_eq!(err, ArrFormError { written: 16, capacity: 16, truncated: true });
+        assert_eq!(af.as_str(), "liquidity 184467");
+        assert!(af.is_truncated());
+
+        // the buffer can be reused after an overflow
+        af.try_format(format_args!("{}", 42)).unwrap();
+        assert_eq!(af.as_str(), "42");
+        assert!(!af.is_truncated());
+    }
+
+    #[test]
+    fn test_try_format_display_error() {
+        let mut af = ArrForm::<16>::new();
+        let err = af.try_format(format_args!("{}", Failing)).unwrap_err();
+        assert_eq!(err, ArrFormError { written: 7, capacity: 16, truncated: false });
+        assert_eq!(err.to_string(), "formatting failed after 7 of 16 bytes");
+    }
+
+    #[test]
+    fn test_try_arrform() {
+        let af = try_arrform!(32, "reserve {}", 3).unwrap();
+        assert_eq!(af.as_str(), "reserve 3");
+        let err = try_arrform!(4, "reserve {}", 3).unwrap_err();
+        assert_eq!(err.written, 4);
+    }
+
+    #[test]
+    fn test_truncates_on_char_boundary() {
+        let mut af = ArrForm::<8>::new();
+        // "é" is two bytes, "€" three and "🦀" four
+        let err = af.try_format(format_args!("{}", "é€🦀")).unwrap_err();
+        assert_eq!(af.as_str(), "é€");
+        assert_eq!(err.written, 5);
+        assert!(err.truncated);
+    }
+
+    fn check_truncation<const BUF_SIZE: usize>(pieces: &[String]) -> Result<(), TestCaseError> {
+        let mut af = ArrForm::<BUF_SIZE>::new();
+        let mut expected = String::new();
+        for piece in pieces {
+            expected.push_str(piece);
+            if af.write_str(piece).is_err() {
+                break;
+            }
+        }
+        prop_assert!(core::str::from_utf8(af.as_bytes()).is_ok());
+        prop_assert!(expected.starts_with(af.as_str()));
+        prop_assert!(af.as_str().len() <= BUF_SIZE);
+        // at most one partial character is dropped when truncating
+        prop_assert!(af.is_truncated() || af.as_str() == expected);
+        if af.is_truncated() {
+            prop_assert!(BUF_SIZE - af.as_str().len() < 4);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_buffer_is_empty() {
+        let af = ArrForm::<64>::new();
+        assert_eq!(af.as_bytes(), b"");
+        assert_eq!(af.as_str(), "");
+        let af = ArrForm::<64>::default();
+        assert_eq!(af.as_str(), "");
+        let af = ArrForm::<0>::new();
+        assert_eq!(af.as_str(), "");
+    }
+
+    #[test]
+    fn test_fill_whole_buffer() {
+        let mut af = ArrForm::<8>::new();
+        af.format(format_args!("{}", 12345678)).unwrap();
+        assert_eq!(af.as_bytes(), b"12345678");
+        assert!(af.write_str("9").is_err());
+        assert_eq!(af.as_str(), "12345678");
+        let mut af = ArrForm::<0>::new();
+        assert!(af.write_str("").is_ok());
+        assert!(af.write_str("x").is_err());
+        assert_eq!(af.as_str(), "");
+    }
+
+    #[test]
+    fn test_reuse_shorter_message() {
+        // only the initialized prefix of the previous message may be exposed
+        let mut af = ArrForm::<32>::new();
+        af.format(format_args!("a much longer {}", "message")).unwrap();
+        af.format(format_args!("{}", 1)).unwrap();
+        assert_eq!(af.as_bytes(), b"1");
+    }
+
+    #[test]
+    fn test_moved_buffer() {
+        fn make() -> ArrForm<16> {
+            arrform!(16, "{}-{}", "moved", 7)
+        }
+        let af = make();
+        assert_eq!(af.as_str(), "moved-7");
+    }
+
+    proptest! {
+        #[test]
+        #[cfg_attr(miri, ignore)]
+        fn prop_as_str_is_valid_utf8(pieces in prop::collection::vec(any::<String>(), 0..8)) {
+            check_truncation::<0>(&pieces)?;
+            check_truncation::<1>(&pieces)?;
+            check_truncation::<3>(&pieces)?;
+            check_truncation::<7>(&pieces)?;
+            check_truncation::<32>(&pieces)?;
+            check_truncation::<64>(&pieces)?;
+        }
+    }
+
+    #[test]
+    fn test_overflow_policies() {
+        macro_rules! args {
+            () => {
+                format_args!("{} {}", "liquidity", u64::MAX)
+            };
+        }
+
+        let mut af = ArrForm::<16>::new();
+        assert!(af.format(args!()).is_err());
+        assert_eq!(af.as_str(), "liquidity 184467");
+
+        let mut af = ArrForm::<16>::with_policy(OverflowPolicy::Truncate);
+        assert!(af.format(args!()).is_ok());
+        assert_eq!(af.as_str(), "liquidity 184467");
+        assert!(af.is_truncated());
+
+        let mut af = ArrForm::<16>::with_policy(OverflowPolicy::TruncateWithEllipsis);
+        assert!(af.try_format(args!()).is_ok());
+        assert_eq!(af.as_str(), "liquidity 184…");
+        assert_eq!(af.as_bytes().len(), 16);
+
+        // later writes don't append to truncated text
+        af.write_str("x").unwrap();
+        assert_eq!(af.as_str(), "liquidity 184…");
+
+        // the policy survives reuse of the buffer
+        af.format(format_args!("{}", 1)).unwrap();
+        assert_eq!(af.as_str(), "1");
+        assert!(!af.is_truncated());
+        assert_eq!(af.policy(), OverflowPolicy::TruncateWithEllipsis);
+    }
+
+    #[test]
+    fn test_ellipsis_on_char_boundary() {
+        let mut af = ArrForm::<8>::with_policy(OverflowPolicy::TruncateWithEllipsis);
+        af.format(format_args!("{}", "ab€€€")).unwrap();
+        assert_eq!(af.as_str(), "ab€…");
+        let mut af = ArrForm::<7>::with_policy(OverflowPolicy::TruncateWithEllipsis);
+        af.format(format_args!("{}", "ab€€€")).unwrap();
+        assert_eq!(af.as_str(), "ab…");
+        let mut af = ArrForm::<2>::with_policy(OverflowPolicy::TruncateWithEllipsis);
+        af.format(format_args!("{}", "abc")).unwrap();
+        assert_eq!(af.as_str(), "ab");
+        let mut af = ArrForm::<3>::with_policy(OverflowPolicy::TruncateWithEllipsis);
+        af.format(format_args!("{}", "abcd")).unwrap();
+        assert_eq!(af.as_str(), "…");
+    }
+
+    #[test]
+    #[should_panic(expected = "Buffer overflow")]
+    fn test_panic_policy() {
+        let mut af = ArrForm::<4>::with_policy(OverflowPolicy::Panic);
+        let _ = af.format(format_args!("{}", 123456));
+    }
+
+    #[test]
+    fn test_string_traits() {
+        use std::collections::HashSet;
+
+        let af = arrform!(32, "reserve {}", 3);
+        assert_eq!(af, "reserve 3");
+        assert_eq!("reserve 3", af);
+        assert_eq!(af, *"reserve 3");
+        assert_eq!(af, arrform!(64, "reserve {}", 3));
+        assert_ne!(af, arrform!(32, "reserve {}", 4));
+        assert_eq!(format!("{}", af), "reserve 3");
+        assert_eq!(format!("{:>10}", af), " reserve 3");
+        assert_eq!(format!("{:?}", af), "\"reserve 3\"");
+        assert!(af.starts_with("reserve"));
+        let as_str: &str = af.as_ref();
+        let as_bytes: &[u8] = af.as_ref();
+        assert_eq!(as_str.as_bytes(), as_bytes);
+
+        let mut set = HashSet::new();
+        set.insert(af.clone());
+        assert!(set.contains("reserve 3"));
+
+        let cloned = ArrForm::<8>::with_policy(OverflowPolicy::Truncate).clone();
+        assert_eq!(cloned.policy(), OverflowPolicy::Truncate);
+        assert_eq!(ArrForm::<8>::default(), "");
+    }
+
+    #[test]
+    fn test_length_methods() {
+        let mut af = arrform!(16, "{}", "€uro");
+        assert_eq!(af.len(), 6);
+        assert!(!af.is_empty());
+        assert_eq!(af.capacity(), 16);
+        assert_eq!(af.remaining(), 10);
+
+        af.truncate(10);
+        assert_eq!(af, "€uro");
+        af.truncate(4);
+        assert_eq!(af, "€u");
+        af.clear();
+        assert!(af.is_empty());
+        assert_eq!(af.remaining(), 16);
+    }
+
+    #[test]
+    #[should_panic(expected = "char boundary")]
+    fn test_truncate_inside_char() {
+        let mut af = arrform!(16, "{}", "€uro");
+        af.truncate(1);
+    }
+
+    #[test]
+    fn test_truncate_resumes_writing() {
+        let mut af = ArrForm::<4>::with_policy(OverflowPolicy::Truncate);
+        af.format(format_args!("{}", 123456)).unwrap();
+        assert!(af.is_truncated());
+        af.truncate(2);
+        af.write_str("ab").unwrap();
+        assert_eq!(af, "12ab");
+    }
+
+    #[test]
+    fn test_append() {
+        let mut af = ArrForm::<16>::new();
+        af.push_str("ids:").unwrap();
+        for id in 0..3 {
+            af.append(format_args!(" {}", id)).unwrap();
+        }
+        af.push_char('€').unwrap();
+        assert_eq!(af, "ids: 0 1 2€");
+        assert!(af.push_str("overflow").is_err());
+        assert_eq!(af, "ids: 0 1 2€ove");
+
+        // format still starts over
+        af.format(format_args!("{}", 1)).unwrap();
+        af.append(format_args!("{}", 2)).unwrap();
+        assert_eq!(af, "12");
+    }
+}
@@ -0,0 +1,281 @@
+//! the stack buckets used by `omsg!`. a message is formatted into the smallest bucket that its
+//! size bound fits into, and whenever a bucket turns out to be too small the message is
+//! formatted again into the next larger bucket. messages which don't fit into the largest
+//! bucket are formatted on the heap, so logging a message can never abort the transaction.
+//! `omsg_truncate!` instead truncates them to the largest bucket, ending them with a "…" marker,
+//! so that its messages never touch the heap.
+
+use crate::fast::OmsgWrite;
+use crate::{ArrForm, OverflowPolicy};
+use core::fmt;
+use solana_program::log::sol_log;
+
+/// the sizes of the stack buffers a message can be formatted into, smallest first
+pub const BUCKETS: [usize; 6] = [32, 64, 128, 256, 512, 768];
+
+/// formats and logs a message into a single bucket, see [try_log]
+type TryBucket = fn(&mut Message) -> Attempt;
+
+/// the function formatting into each of the [BUCKETS]
+const TRY_BUCKETS: [(usize, TryBucket); BUCKETS.len()] = [
+    (BUCKETS[0], try_log::<{ BUCKETS[0] }>),
+    (BUCKETS[1], try_log::<{ BUCKETS[1] }>),
+    (BUCKETS[2], try_log::<{ BUCKETS[2] }>),
+    (BUCKETS[3], try_log::<{ BUCKETS[3] }>),
+    (BUCKETS[4], try_log::<{ BUCKETS[4] }>),
+    (BUCKETS[5], try_log::<{ BUCKETS[5] }>),
+];
+
+/// how a message was logged by the `omsg!` family of macros
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LogPath {
+    /// a message without placeholders, logged without formatting
+    Literal,
+    /// formatted into the stack bucket of the given size
+    Stack { bucket: usize },
+    /// too long for every bucket and formatted on the heap
+    Heap,
+    /// cut off to fit a fixed size buffer, used by `omsg_truncate!` messages which don't fit the
+    /// largest bucket, `okv!` lines which don't fit its buffer and `ojson!` objects whose fields
+    /// were left out, which are still valid json
+    Truncated,
+}
+
+/// the outcome of logging a message with the `omsg!` family of macros
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Logged {
+    /// how the message was formatted
+    pub path: LogPath,
+    /// the number of bytes logged
+    pub len: usize,
+}
+
+impl Logged {
+    /// returns true if the message was logged without touching the heap
+    pub fn is_stack(&self) -> bool {
+        self.path != LogPath::Heap
+    }
+}
+
+/// logs a message that was fully assembled at compile time
+#[doc(hidden)]
+#[inline(always)]
+pub fn log_literal(message: &'static str) -> Logged {
+    sol_log(message);
+    Logged { path: LogPath::Literal, len: message.len() }
+}
+
+/// logs the text of a fixed size buffer, such as an `okv!` or `ojson!` line or a buffer flushed
+/// by `omsg_flush!`, reporting it as [LogPath::Truncated] if `truncated` is true
+pub(crate) fn log_line<const BUF_SIZE: usize>(af: &ArrForm<BUF_SIZE>, truncated: bool) -> Logged {
+    sol_log(af.as_str());
+    let path = if truncated { LogPath::Truncated } else { LogPath::Stack { bucket: BUF_SIZE } };
+    Logged { path, len: af.len() }
+}
+
+/// logs `prefix` followed by `args`, starting with the smallest bucket that can hold
+/// `size_hint` bytes. a `size_hint` of `usize::MAX` means the size of the message is
+/// unknown, in which case every bucket is tried in turn.
+///
+/// this is the single entry point of every formatted `omsg!` call site, which only builds the
+/// `fmt::Arguments`. it is neither generic nor inlined, so the formatting code and the bucket
+/// selection are compiled once per program instead of once per log site
+#[doc(hidden)]
+#[inline(never)]
+pub fn log_args(prefix: &str, size_hint: usize, args: fmt::Arguments) -> Logged {
+    log_message(size_hint, Message::Args(prefix, args), log_fallback)
+}
+
+/// the entry point of `omsg_truncate!`, the same as [log_args] except that messages which
+/// don't fit the largest bucket are truncated to it instead of formatted on the heap
+#[doc(hidden)]
+#[inline(never)]
+pub fn log_args_truncated(prefix: &str, size_hint: usize, args: fmt::Arguments) -> Logged {
+    log_message(size_hint, Message::Args(prefix, args), log_truncated)
+}
+
+/// the entry point of `omsg_fast!`, which logs the pieces `write` pushes into the writer it
+/// is given. buckets are picked the same way as for [log_args], so `write` is called again
+/// whenever the message overflows a bucket
+#[doc(hidden)]
+#[inline(never)]
+pub fn log_pieces(size_hint: usize, write: &mut dyn FnMut(&mut dyn OmsgWrite)) -> Logged {
+    log_message(size_hint, Message::Pieces(write), log_fallback)
+}
+
+/// the text of a message, as given to one of the entry points
+enum Message<'a> {
+    /// a prefix followed by the arguments of `omsg!`
+    Args(&'a str, fmt::Arguments<'a>),
+    /// the pieces written by `omsg_fast!`
+    Pieces(&'a mut dyn FnMut(&mut dyn OmsgWrite)),
+}
+
+impl Message<'_> {
+    /// writes the message into `w`, returning false if a `Display` impl failed
+    fn write_to<W: fmt::Write + OmsgWrite>(&mut self, w: &mut W) -> bool {
+        match self {
+            Message::Args(prefix, args) => w.write_str(prefix).is_ok() && fmt::write(w, *args).is_ok(),
+            Message::Pieces(write) => {
+                write(w);
+                true
+            }
+        }
+    }
+}
+
+/// the outcome of formatting a message into one bucket
+enum Attempt {
+    /// the message fit and was logged with this length
+    Logged(usize),
+    /// the message didn't fit, and is formatted again into the next larger bucket
+    Overflow,
+    /// a `Display` impl returned an error, which no larger bucket can fix
+    Failed,
+}
+
+/// logs a message which didn't fit into any bucket, or whose `Display` impl failed
+type Fallback = fn(Message) -> Logged;
+
+fn log_message(size_hint: usize, mut message: Message, fallback: Fallback) -> Logged {
+    let start = if size_hint == usize::MAX { 0 } else { size_hint };
+    for (bucket, try_bucket) in TRY_BUCKETS {
+        if start <= bucket {
+            match try_bucket(&mut message) {
+                Attempt::Logged(len) => return Logged { path: LogPath::Stack { bucket }, len },
+                Attempt::Overflow => continue,
+                Attempt::Failed => break,
+            }
+        }
+    }
+    fallback(message)
+}
+
+/// logs messages which don't fit into any bucket on the heap. messages whose `Display` impl
+/// failed end up here as well, since a failing impl must not abort the transaction, so the
+/// partially formatted message is logged instead of panicking like `format!` does
+fn log_fallback(mut message: Message) -> Logged {
+    let mut text = String::new();
+    message.write_to(&mut text);
+    sol_log(&text);
+    Logged { path: LogPath::Heap, len: text.len() }
+}
+
+/// logs messages which don't fit into any bucket truncated to the largest bucket, ending with a
+/// "…" marker. like [log_fallback] it also logs the partial text of a failing `Display` impl
+fn log_truncated(mut message: Message) -> Logged {
+    let mut af = ArrForm::<{ BUCKETS[BUCKETS.len() - 1] }>::with_policy(OverflowPolicy::TruncateWithEllipsis);
+    message.write_to(&mut af);
+    sol_log(af.as_str());
+    let path = if af.is_truncated() { LogPath::Truncated } else { LogPath::Stack { bucket: af.capacity() } };
+    Logged { path, len: af.len() }
+}
+
+/// formats `message` into a `BUF_SIZE` bucket, logging it if it fit. an error is only an
+/// overflow if the buffer ran out of space, any other error comes from a `Display` impl.
+/// kept out of line so that only the bucket in use occupies the stack frame
+#[inline(never)]
+fn try_log<const BUF_SIZE: usize>(message: &mut Message) -> Attempt {
+    let mut af = ArrForm::<BUF_SIZE>::new();
+    let written = message.write_to(&mut af);
+    if af.is_truncated() {
+        record_overflow();
+        Attempt::Overflow
+    } else if written {
+        sol_log(af.as_str());
+        Attempt::Logged(af.len())
+    } else {
+        Attempt::Failed
+    }
+}
+
+#[cfg(all(debug_assertions, not(target_os = "solana")))]
+static OVERFLOWS: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(0);
+
+#[inline(always)]
+fn record_overflow() {
+    #[cfg(all(debug_assertions, not(target_os = "solana")))]
+    OVERFLOWS.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+}
+
+/// returns how many times a message overflowed its bucket and had to be formatted again.
+/// only available in native debug builds, as programs can't have writable static data on chain
+#[cfg(all(debug_assertions, not(target_os = "solana")))]
+pub fn overflow_count() -> usize {
+    OVERFLOWS.load(core::sync::atomic::Ordering::Relaxed)
+}
+
+#[cfg(all(test, debug_assertions))]
+mod test {
+    use super::*;
+
+    struct Padded(usize);
+
+    impl fmt::Display for Padded {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            for _ in 0..self.0 {
+                f.write_str("x")?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_retries_larger_buckets() {
+        let before = overflow_count();
+        // the size of `Padded` is unknown, so every bucket below 512 overflows
+        let logged = log_args("", usize::MAX, format_args!("{}", Padded(300)));
+        assert_eq!(logged, Logged { path: LogPath::Stack { bucket: 512 }, len: 300 });
+        assert!(overflow_count() >= before + 4);
+    }
+
+    /// fails after writing some text, counting how often it was formatted
+    struct Failing<'a>(&'a core::cell::Cell<usize>);
+
+    impl fmt::Display for Failing<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.set(self.0.get() + 1);
+            f.write_str("partial")?;
+            Err(fmt::Error)
+        }
+    }
+
+    #[test]
+    fn test_display_error_is_not_retried() {
+        let calls = core::cell::Cell::new(0);
+        let logs = crate::testing::capture_logs(|| {
+            log_args("", usize::MAX, format_args!("failing {}", Failing(&calls)));
+        });
+        // formatted once into the first bucket and once more by the fallback
+        assert_eq!(calls.get(), 2);
+        assert_eq!(logs, vec!["failing partial"]);
+    }
+
+    #[test]
+    fn test_underestimated_size_hint() {
+        let before = overflow_count();
+        let logged = log_args("", 10, format_args!("{}", Padded(100)));
+        assert_eq!(logged.path, LogPath::Stack { bucket: 128 });
+        assert!(overflow_count() >= before + 2);
+    }
+
+    #[test]
+    fn test_heap_fallback() {
+        let logged = log_args("", usize::MAX, format_args!("{}", Padded(1000)));
+        assert_eq!(logged, Logged { path: LogPath::Heap, len: 1000 });
+        assert!(!logged.is_stack());
+        let logged = log_args("[lib.rs:1] ", 1000, format_args!("{}", Padded(1000)));
+        assert_eq!(logged.len, 1011);
+    }
+
+    #[test]
+    fn test_truncated_fallback() {
+        let logs = crate::testing::capture_logs(|| {
+            let logged = log_args_truncated("", usize::MAX, format_args!("{}", Padded(1000)));
+            assert_eq!(logged, Logged { path: LogPath::Truncated, len: 768 });
+            let logged = log_args_truncated("", usize::MAX, format_args!("{}", Padded(300)));
+            assert_eq!(logged.path, LogPath::Stack { bucket: 512 });
+        });
+        assert_eq!(logs[0], format!("{}…", "x".repeat(765)));
+    }
+}
@@ -0,0 +1,488 @@
+//! allocation free text encodings of binary data. the encodings are written into buffers on
+//! the stack, unlike the `Display` impl of `Pubkey`, which allocates a base58 `String` and
+//! breaks the stack only formatting of `omsg!` whenever an address is logged. byte slices
+//! such as discriminators, hashes and fragments of account data can be logged as hex, base64
+//! or base58 through [Hex], [B64] and [B58], instead of the long decimal list of `{:?}`.
+//! like the `Display` impl of `str`, the wrappers honour the width, fill and alignment of a
+//! format spec such as `{:>20}`, but unlike it they ignore the precision.
+//!
+//! ```
+//! use omsg::encoding::OmsgPubkey;
+//! use omsg::{omsg, ArrForm};
+//! use solana_program::pubkey::Pubkey;
+//!
+//! let key = Pubkey::new_from_array([7; 32]);
+//! let mut af = ArrForm::<64>::new();
+//! af.push_pubkey(&key).unwrap();
+//! assert_eq!(af.as_str(), key.to_string());
+//!
+//! af.clear();
+//! af.push_pubkey_short(&key).unwrap();
+//! assert_eq!(af, "US51…ELFx");
+//!
+//! // the wrappers format pubkeys on the stack through `omsg!`
+//! omsg!("refreshed reserve {} of {}", OmsgPubkey::new(&key), OmsgPubkey::short(&key));
+//! ```
+//!
+//! ```
+//! use omsg::encoding::{B58, B64, Hex};
+//! use omsg::ArrForm;
+//!
+//! let data = [0xde, 0xad, 0xbe, 0xef];
+//! let mut af = ArrForm::<64>::new();
+//! af.push_hex_upper(&data).unwrap();
+//! assert_eq!(af, "DEADBEEF");
+//!
+//! assert_eq!(Hex(&data).to_string(), "deadbeef");
+//! assert_eq!(B64(&data).to_string(), "3q2+7w==");
+//! assert_eq!(B58(&data).to_string(), "6h8cQN");
+//! ```
+
+use crate::fast::{OmsgDisplay, OmsgWrite};
+use crate::sizing::MaxDisplayLen;
+use crate::ArrForm;
+use core::fmt;
+use core::fmt::Write as _;
+use core::str::from_utf8_unchecked;
+use solana_program::pubkey::Pubkey;
+
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const HEX_LOWER: &[u8; 16] = b"0123456789abcdef";
+const HEX_UPPER: &[u8; 16] = b"0123456789ABCDEF";
+
+/// the longest input encoded as base58. the encoding takes time quadratic in the length of
+/// the input, and is meant for short values such as keys, hashes and signatures. longer inputs
+/// are written as a marker such as `<base58: 100 bytes, too long>` instead
+pub const BASE58_MAX_INPUT: usize = 64;
+
+/// the text around the length of an input too long to encode as base58
+const BASE58_TOO_LONG: (&str, &str) = ("<base58: ", " bytes, too long>");
+
+/// the length of the marker written for an input too long to encode as base58
+const BASE58_TOO_LONG_LEN: usize = BASE58_TOO_LONG.0.len() + MAX_USIZE_LEN + BASE58_TOO_LONG.1.len();
+
+/// the number of digits of `usize::MAX`
+const MAX_USIZE_LEN: usize = 20;
+
+/// the length of the base58 encoding of [BASE58_MAX_INPUT] bytes
+pub(crate) const BASE58_MAX_LEN: usize = base58_max_len(BASE58_MAX_INPUT);
+
+/// the length of the base58 encoding of the largest pubkey
+const PUBKEY_LEN: usize = 44;
+
+/// the number of characters kept at either end of a shortened pubkey
+const SHORT_PUBKEY_ENDS: usize = 4;
+
+/// the marker between the ends of a shortened pubkey
+const SHORT_PUBKEY_MARKER: &str = "…";
+
+/// the number of input bytes encoded at a time by the hex and base64 encoders
+const CHUNK: usize = 48;
+
+/// an upper bound for the length of the base58 encoding of `len` bytes, as each byte
+/// contributes log(256) / log(58) < 1.38 characters
+const fn base58_max_len(len: usize) -> usize {
+    len * 138 / 100 + 1
+}
+
+/// returns the base58 encoding of `bytes` written to `buf`, or `None` if `bytes` is longer
+/// than [BASE58_MAX_INPUT]
+fn base58<'a>(bytes: &[u8], buf: &'a mut [u8; BASE58_MAX_LEN]) -> Option<&'a str> {
+    if bytes.len() > BASE58_MAX_INPUT {
+        return None;
+    }
+    // the digits of the encoding in base 58, least significant first
+    let mut digits = [0u8; BASE58_MAX_LEN];
+    let mut len = 0;
+    for &byte in bytes {
+        let mut carry = byte as u32;
+        for digit in digits[..len].iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits[len] = (carry % 58) as u8;
+            len += 1;
+            carry /= 58;
+        }
+    }
+    // every leading zero byte is encoded as a leading '1'
+    let zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+    buf[..zeros].fill(BASE58_ALPHABET[0]);
+    for (out, digit) in buf[zeros..zeros + len].iter_mut().zip(digits[..len].iter().rev()) {
+        *out = BASE58_ALPHABET[*digit as usize];
+    }
+    // the base58 alphabet is ascii
+    Some(unsafe { from_utf8_unchecked(&buf[..zeros + len]) })
+}
+
+/// returns the base58 encoding of `key`, written to `buf`
+pub(crate) fn pubkey_base58<'a>(key: &Pubkey, buf: &'a mut [u8; BASE58_MAX_LEN]) -> &'a str {
+    match base58(key.as_ref(), buf) {
+        Some(encoded) => encoded,
+        None => unreachable!("a pubkey is shorter than the longest base58 input"),
+    }
+}
+
+/// passes the base58 encoding of `bytes` to `f`, or a marker with the length of `bytes` if it
+/// is too long to encode
+fn base58_pieces(bytes: &[u8], mut f: impl FnMut(&str) -> fmt::Result) -> fmt::Result {
+    if let Some(encoded) = base58(bytes, &mut [0; BASE58_MAX_LEN]) {
+        return f(encoded);
+    }
+    let mut len = ArrForm::<MAX_USIZE_LEN>::new();
+    bytes.len().omsg_fmt(&mut len);
+    f(BASE58_TOO_LONG.0)?;
+    f(len.as_str())?;
+    f(BASE58_TOO_LONG.1)
+}
+
+/// an upper bound for the text [base58_pieces] writes for `len` bytes
+const fn base58_display_len(len: usize) -> usize {
+    if len <= BASE58_MAX_INPUT {
+        base58_max_len(len)
+    } else {
+        BASE58_TOO_LONG_LEN
+    }
+}
+
+/// passes the shortened base58 encoding of `key` to `f` in pieces, such as `4Nd1…kX9q`
+fn short_pubkey_pieces(key: &Pubkey, mut f: impl FnMut(&str) -> fmt::Result) -> fmt::Result {
+    let mut buf = [0; BASE58_MAX_LEN];
+    let encoded = pubkey_base58(key, &mut buf);
+    f(&encoded[..SHORT_PUBKEY_ENDS])?;
+    f(SHORT_PUBKEY_MARKER)?;
+    f(&encoded[encoded.len() - SHORT_PUBKEY_ENDS..])
+}
+
+/// passes the hex encoding of `bytes` to `f` in pieces
+fn hex_pieces(bytes: &[u8], alphabet: &[u8; 16], mut f: impl FnMut(&str) -> fmt::Result) -> fmt::Result {
+    let mut buf = [0u8; 2 * CHUNK];
+    for chunk in bytes.chunks(CHUNK) {
+        for (out, &byte) in buf.chunks_exact_mut(2).zip(chunk) {
+            out[0] = alphabet[(byte >> 4) as usize];
+            out[1] = alphabet[(byte & 0xf) as usize];
+        }
+        // the hex alphabet is ascii
+        f(unsafe { from_utf8_unchecked(&buf[..2 * chunk.len()]) })?;
+    }
+    Ok(())
+}
+
+/// passes the padded base64 encoding of `bytes` to `f` in pieces
+fn base64_pieces(bytes: &[u8], mut f: impl FnMut(&str) -> fmt::Result) -> fmt::Result {
+    let mut buf = [0u8; CHUNK / 3 * 4];
+    for chunk in bytes.chunks(CHUNK) {
+        for (out, group) in buf.chunks_exact_mut(4).zip(chunk.chunks(3)) {
+            let byte = |i: usize| group.get(i).copied().unwrap_or(0) as u32;
+            let bits = byte(0) << 16 | byte(1) << 8 | byte(2);
+            out[0] = BASE64_ALPHABET[(bits >> 18) as usize & 63];
+            out[1] = BASE64_ALPHABET[(bits >> 12) as usize & 63];
+            out[2] = if group.len() > 1 { BASE64_ALPHABET[(bits >> 6) as usize & 63] } else { b'=' };
+            out[3] = if group.len() > 2 { BASE64_ALPHABET[bits as usize & 63] } else { b'=' };
+        }
+        // the base64 alphabet is ascii
+        f(unsafe { from_utf8_unchecked(&buf[..chunk.len().div_ceil(3) * 4]) })?;
+    }
+    Ok(())
+}
+
+impl<const BUF_SIZE: usize> ArrForm<BUF_SIZE> {
+    /// appends the base58 encoding of `key`, the same text as its `Display` impl produces
+    pub fn push_pubkey(&mut self, key: &Pubkey) -> fmt::Result {
+        self.push_str(pubkey_base58(key, &mut [0; BASE58_MAX_LEN]))
+    }
+
+    /// appends the first and last four characters of the base58 encoding of `key`, separated
+    /// by "…"
+    pub fn push_pubkey_short(&mut self, key: &Pubkey) -> fmt::Result {
+        short_pubkey_pieces(key, |piece| self.push_str(piece))
+    }
+
+    /// appends the lowercase hex encoding of `bytes`
+    pub fn push_hex(&mut self, bytes: &[u8]) -> fmt::Result {
+        hex_pieces(bytes, HEX_LOWER, |piece| self.push_str(piece))
+    }
+
+    /// appends the uppercase hex encoding of `bytes`
+    pub fn push_hex_upper(&mut self, bytes: &[u8]) -> fmt::Result {
+        hex_pieces(bytes, HEX_UPPER, |piece| self.push_str(piece))
+    }
+
+    /// appends the standard, padded base64 encoding of `bytes`
+    pub fn push_base64(&mut self, bytes: &[u8]) -> fmt::Result {
+        base64_pieces(bytes, |piece| self.push_str(piece))
+    }
+
+    /// appends the base58 encoding of `bytes`. inputs longer than [BASE58_MAX_INPUT] bytes
+    /// are not encoded, and append a marker such as `<base58: 100 bytes, too long>` instead
+    pub fn push_base58(&mut self, bytes: &[u8]) -> fmt::Result {
+        base58_pieces(bytes, |piece| self.push_str(piece))
+    }
+}
+
+/// displays a pubkey in base58 without allocating, use it in place of the pubkey in `omsg!`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct OmsgPubkey<'a> {
+    key: &'a Pubkey,
+    short: bool,
+}
+
+impl<'a> OmsgPubkey<'a> {
+    /// displays the whole encoding of `key`
+    pub const fn new(key: &'a Pubkey) -> Self {
+        OmsgPubkey { key, short: false }
+    }
+
+    /// displays the first and last four characters of the encoding of `key`, such as `4Nd1…kX9q`
+    pub const fn short(key: &'a Pubkey) -> Self {
+        OmsgPubkey { key, short: true }
+    }
+}
+
+impl fmt::Display for OmsgPubkey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.short {
+            let mut af = ArrForm::<{ 2 * SHORT_PUBKEY_ENDS + SHORT_PUBKEY_MARKER.len() }>::new();
+            af.push_pubkey_short(self.key)?;
+            f.pad(af.as_str())
+        } else {
+            f.pad(pubkey_base58(self.key, &mut [0; BASE58_MAX_LEN]))
+        }
+    }
+}
+
+impl OmsgDisplay for OmsgPubkey<'_> {
+    fn omsg_fmt<W: OmsgWrite + ?Sized>(&self, w: &mut W) {
+        if self.short {
+            let _ = short_pubkey_pieces(self.key, |piece| {
+                w.push_str(piece);
+                Ok(())
+            });
+        } else {
+            w.push_str(pubkey_base58(self.key, &mut [0; BASE58_MAX_LEN]));
+        }
+    }
+}
+
+impl MaxDisplayLen for OmsgPubkey<'_> {
+    fn max_display_len(&self) -> usize {
+        if self.short {
+            2 * SHORT_PUBKEY_ENDS + SHORT_PUBKEY_MARKER.len()
+        } else {
+            PUBKEY_LEN
+        }
+    }
+}
+
+/// displays a byte slice as lowercase hex without allocating
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Hex<'a>(pub &'a [u8]);
+
+/// displays a byte slice as standard, padded base64 without allocating
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct B64<'a>(pub &'a [u8]);
+
+/// displays a byte slice as base58 without allocating. slices longer than [BASE58_MAX_INPUT]
+/// bytes are displayed as a marker such as `<base58: 100 bytes, too long>`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct B58<'a>(pub &'a [u8]);
+
+macro_rules! impl_encoding_wrappers {
+    ($($wrapper:ident => |$bytes:ident, $f:ident| $pieces:expr, max_len: |$len:ident| $max_len:expr;)*) => {
+        $(
+            impl OmsgDisplay for $wrapper<'_> {
+                fn omsg_fmt<W: OmsgWrite + ?Sized>(&self, w: &mut W) {
+                    let ($bytes, $f) = (self.0, |piece: &str| {
+                        w.push_str(piece);
+                        Ok(())
+                    });
+                    let _ = $pieces;
+                }
+            }
+
+            impl MaxDisplayLen for $wrapper<'_> {
+                fn max_display_len(&self) -> usize {
+                    let $len = self.0.len();
+                    $max_len
+                }
+            }
+        )*
+    };
+}
+
+impl_encoding_wrappers! {
+    Hex => |bytes, f| hex_pieces(bytes, HEX_LOWER, f), max_len: |len| 2 * len;
+    B64 => |bytes, f| base64_pieces(bytes, f), max_len: |len| len.div_ceil(3) * 4;
+    B58 => |bytes, f| base58_pieces(bytes, f), max_len: |len| base58_display_len(len);
+}
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        pad_pieces(f, 2 * self.0.len(), |f| hex_pieces(self.0, HEX_LOWER, |piece| f.write_str(piece)))
+    }
+}
+
+impl fmt::Display for B64<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        pad_pieces(f, self.0.len().div_ceil(3) * 4, |f| base64_pieces(self.0, |piece| f.write_str(piece)))
+    }
+}
+
+impl fmt::Display for B58<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the length of the encoding isn't known up front, but it is short enough to buffer
+        let mut af = ArrForm::<BASE58_MAX_LEN>::new();
+        base58_pieces(self.0, |piece| af.push_str(piece))?;
+        pad_pieces(f, af.len(), |f| f.write_str(&af))
+    }
+}
+
+/// writes the `len` characters `write` passes to `f`, padded to the width of `f` with its fill
+/// and alignment, and left aligned by default like strings
+fn pad_pieces(
+    f: &mut fmt::Formatter<'_>,
+    len: usize,
+    write: impl FnOnce(&mut fmt::Formatter<'_>) -> fmt::Result,
+) -> fmt::Result {
+    let padding = f.width().unwrap_or(0).saturating_sub(len);
+    let (before, after) = match f.align() {
+        Some(fmt::Alignment::Right) => (padding, 0),
+        Some(fmt::Alignment::Center) => (padding / 2, padding - padding / 2),
+        Some(fmt::Alignment::Left) | None => (0, padding),
+    };
+    let fill = f.fill();
+    for _ in 0..before {
+        f.write_char(fill)?;
+    }
+    write(f)?;
+    for _ in 0..after {
+        f.write_char(fill)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pubkey_base58() {
+        let mut last_byte = [0u8; 32];
+        last_byte[31] = 1;
+        for key in [
+            Pubkey::default(),
+            Pubkey::new_from_array([255; 32]),
+            Pubkey::new_from_array(last_byte),
+            Pubkey::new_unique(),
+            solana_program::system_program::id(),
+        ] {
+            assert_eq!(pubkey_base58(&key, &mut [0; BASE58_MAX_LEN]), key.to_string());
+        }
+    }
+
+    #[test]
+    fn test_short_pubkey() {
+        let key = Pubkey::new_unique();
+        let encoded = key.to_string();
+        let short = OmsgPubkey::short(&key).to_string();
+        assert_eq!(short, format!("{}…{}", &encoded[..4], &encoded[encoded.len() - 4..]));
+        assert_eq!(short.len(), OmsgPubkey::short(&key).max_display_len());
+        let mut text = String::new();
+        OmsgPubkey::short(&key).omsg_fmt(&mut text);
+        assert_eq!(text, short);
+    }
+
+    #[test]
+    fn test_push_pubkey_overflow() {
+        let key = Pubkey::new_from_array([255; 32]);
+        let mut af = ArrForm::<8>::new();
+        assert!(af.push_pubkey_short(&key).is_err());
+        assert_eq!(af, "JEKN…W");
+        assert!(af.is_truncated());
+    }
+
+    #[test]
+    fn test_omsg_pubkey_bucket() {
+        let key = Pubkey::new_from_array([255; 32]);
+        let logs = crate::testing::capture_logs(|| {
+            let logged = crate::omsg!("{}", OmsgPubkey::new(&key));
+            assert_eq!(logged.path, crate::LogPath::Stack { bucket: 64 });
+            assert_eq!(logged.len, 44);
+        });
+        assert_eq!(logs, vec![key.to_string()]);
+    }
+
+    #[test]
+    fn test_hex() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let expected: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+        assert_eq!(Hex(&bytes).to_string(), expected);
+        let mut af = ArrForm::<600>::new();
+        af.push_hex_upper(&bytes).unwrap();
+        assert_eq!(af.as_str(), expected.to_uppercase());
+        assert_eq!(Hex(&[]).to_string(), "");
+    }
+
+    #[test]
+    fn test_base64() {
+        // the test vectors of RFC 4648
+        for (input, expected) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(B64(input.as_bytes()).to_string(), expected);
+            assert_eq!(B64(input.as_bytes()).max_display_len(), expected.len());
+        }
+        // inputs spanning several chunks
+        let bytes = [0xffu8; 100];
+        assert_eq!(B64(&bytes).to_string(), format!("{}/w==", "/".repeat(132)));
+    }
+
+    #[test]
+    fn test_base58() {
+        let hash = solana_program::hash::Hash::new_unique();
+        assert_eq!(B58(hash.as_ref()).to_string(), hash.to_string());
+        let signature = [0xffu8; BASE58_MAX_INPUT];
+        assert!(B58(&signature).to_string().len() <= B58(&signature).max_display_len());
+        assert_eq!(B58(&[0, 0, 1]).to_string(), "112");
+        let mut af = ArrForm::<200>::new();
+        af.push_base58(&[0u8; BASE58_MAX_INPUT + 1]).unwrap();
+        assert_eq!(af, "<base58: 65 bytes, too long>");
+    }
+
+    #[test]
+    fn test_padding() {
+        let data = [0xde, 0xad];
+        assert_eq!(format!("[{:>8}]", Hex(&data)), "[    dead]");
+        assert_eq!(format!("[{:8}]", Hex(&data)), "[dead    ]");
+        assert_eq!(format!("[{:*^9}]", B64(&data)), "[**3q0=***]");
+        assert_eq!(format!("[{:>6}]", B58(&data)), "[   Hwr]");
+        assert_eq!(format!("[{:2}]", Hex(&data)), "[dead]");
+        let logs = crate::testing::capture_logs(|| {
+            crate::omsg!("data {:>10}|", Hex(&data));
+        });
+        assert_eq!(logs, vec!["data       dead|"]);
+    }
+
+    #[test]
+    fn test_base58_too_long() {
+        let long = [1u8; 100];
+        assert_eq!(B58(&long).to_string(), "<base58: 100 bytes, too long>");
+        assert!(B58(&long).to_string().len() <= B58(&long).max_display_len());
+        assert_eq!(format!("<base58: {} bytes, too long>", usize::MAX).len(), BASE58_TOO_LONG_LEN);
+        let logs = crate::testing::capture_logs(|| {
+            let logged = crate::omsg!("data {}", B58(&long));
+            assert_eq!(logged.path, crate::LogPath::Stack { bucket: 64 });
+            crate::omsg_fast!("data {}", B58(&long));
+        });
+        assert_eq!(logs, vec!["data <base58: 100 bytes, too long>"; 2]);
+    }
+}
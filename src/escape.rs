@@ -0,0 +1,44 @@
+//! the escaping shared by the quoted values of `okv!` and the strings of `ojson!`. `"` and `\`
+//! are escaped with a backslash, and control characters as `\n`, `\r`, `\t` or `\u00XX`, which
+//! is both a valid json string escape and easy to read in a logfmt line
+
+use crate::ArrForm;
+
+/// appends `value` with `"`, `\` and control characters escaped as in a quoted string
+pub(crate) fn push_escaped<const BUF_SIZE: usize>(af: &mut ArrForm<BUF_SIZE>, value: &str) {
+    // runs of characters which need no escape are pushed at once
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        let escape = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            c if c.is_control() => "",
+            _ => continue,
+        };
+        let _ = af.push_str(&value[start..i]);
+        start = i + c.len_utf8();
+        if escape.is_empty() {
+            // control characters are all below U+0100
+            let _ = af.push_str("\\u00");
+            let _ = af.push_hex(&[c as u8]);
+        } else {
+            let _ = af.push_str(escape);
+        }
+    }
+    let _ = af.push_str(&value[start..]);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_push_escaped() {
+        let mut af = ArrForm::<64>::new();
+        push_escaped(&mut af, "say \"hi\"\\ é\n\t\u{1}\u{85}");
+        assert_eq!(af, r#"say \"hi\"\\ é\n\t\u0001\u0085"#);
+    }
+}
@@ -0,0 +1,203 @@
+//! a lightweight alternative to `core::fmt`, used by `omsg_fast!` and `oformat!`. the format
+//! string is parsed at compile time and turned into direct calls which push literal text and
+//! [OmsgDisplay] values into an [OmsgWrite], in the spirit of `ufmt`. this avoids the
+//! `Formatter` machinery of `core::fmt`, whose code size and compute cost are significant on
+//! chain, at the price of only supporting plain `{}` placeholders without format specs.
+
+use crate::ArrForm;
+use solana_program::pubkey::Pubkey;
+
+/// a destination for the text produced by [OmsgDisplay] implementations
+pub trait OmsgWrite {
+    /// appends `s`. text that doesn't fit is handled by the writer, an `ArrForm` applies its
+    /// overflow policy
+    fn push_str(&mut self, s: &str);
+}
+
+impl<const BUF_SIZE: usize> OmsgWrite for ArrForm<BUF_SIZE> {
+    #[inline]
+    fn push_str(&mut self, s: &str) {
+        // overflows are recorded by the buffer, see `ArrForm::is_truncated`
+        let _ = ArrForm::push_str(self, s);
+    }
+}
+
+impl OmsgWrite for String {
+    #[inline]
+    fn push_str(&mut self, s: &str) {
+        String::push_str(self, s)
+    }
+}
+
+/// formats a value for `omsg_fast!` and `oformat!`, the equivalent of `Display`.
+///
+/// implement this for your own types to use them as `{}` arguments
+/// ```
+/// use omsg::fast::{OmsgDisplay, OmsgWrite};
+/// use omsg::oformat;
+///
+/// struct ReserveIndex(u8);
+///
+/// impl OmsgDisplay for ReserveIndex {
+///     fn omsg_fmt<W: OmsgWrite + ?Sized>(&self, w: &mut W) {
+///         w.push_str("reserve#");
+///         self.0.omsg_fmt(w);
+///     }
+/// }
+///
+/// let af = oformat!(32, "refreshing {}", ReserveIndex(3));
+/// assert_eq!(af, "refreshing reserve#3");
+/// ```
+pub trait OmsgDisplay {
+    fn omsg_fmt<W: OmsgWrite + ?Sized>(&self, w: &mut W);
+}
+
+macro_rules! impl_omsg_display_unsigned {
+    ($($ty:ty => $via:ident),* $(,)?) => {
+        $(
+            impl OmsgDisplay for $ty {
+                #[inline]
+                fn omsg_fmt<W: OmsgWrite + ?Sized>(&self, w: &mut W) {
+                    $via(w, *self as _)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_omsg_display_signed {
+    ($($ty:ty => $via:ident),* $(,)?) => {
+        $(
+            impl OmsgDisplay for $ty {
+                #[inline]
+                fn omsg_fmt<W: OmsgWrite + ?Sized>(&self, w: &mut W) {
+                    if *self < 0 {
+                        w.push_str("-");
+                    }
+                    $via(w, self.unsigned_abs() as _)
+                }
+            }
+        )*
+    };
+}
+
+impl_omsg_display_unsigned! {
+    u8 => write_u64,
+    u16 => write_u64,
+    u32 => write_u64,
+    u64 => write_u64,
+    usize => write_u64,
+    u128 => write_u128,
+}
+
+impl_omsg_display_signed! {
+    i8 => write_u64,
+    i16 => write_u64,
+    i32 => write_u64,
+    i64 => write_u64,
+    isize => write_u64,
+    i128 => write_u128,
+}
+
+fn write_u64<W: OmsgWrite + ?Sized>(w: &mut W, value: u64) {
+    w.push_str(crate::num::u64_digits(value, &mut [0; 20]))
+}
+
+fn write_u128<W: OmsgWrite + ?Sized>(w: &mut W, value: u128) {
+    w.push_str(crate::num::u128_digits(value, &mut [0; 39]))
+}
+
+impl OmsgDisplay for bool {
+    #[inline]
+    fn omsg_fmt<W: OmsgWrite + ?Sized>(&self, w: &mut W) {
+        w.push_str(if *self { "true" } else { "false" })
+    }
+}
+
+impl OmsgDisplay for char {
+    #[inline]
+    fn omsg_fmt<W: OmsgWrite + ?Sized>(&self, w: &mut W) {
+        w.push_str(self.encode_utf8(&mut [0u8; 4]))
+    }
+}
+
+impl OmsgDisplay for str {
+    #[inline]
+    fn omsg_fmt<W: OmsgWrite + ?Sized>(&self, w: &mut W) {
+        w.push_str(self)
+    }
+}
+
+impl OmsgDisplay for String {
+    #[inline]
+    fn omsg_fmt<W: OmsgWrite + ?Sized>(&self, w: &mut W) {
+        w.push_str(self)
+    }
+}
+
+impl<const BUF_SIZE: usize> OmsgDisplay for ArrForm<BUF_SIZE> {
+    #[inline]
+    fn omsg_fmt<W: OmsgWrite + ?Sized>(&self, w: &mut W) {
+        w.push_str(self.as_str())
+    }
+}
+
+impl OmsgDisplay for Pubkey {
+    fn omsg_fmt<W: OmsgWrite + ?Sized>(&self, w: &mut W) {
+        w.push_str(crate::encoding::pubkey_base58(self, &mut [0; crate::encoding::BASE58_MAX_LEN]))
+    }
+}
+
+impl<T: OmsgDisplay + ?Sized> OmsgDisplay for &T {
+    #[inline]
+    fn omsg_fmt<W: OmsgWrite + ?Sized>(&self, w: &mut W) {
+        (**self).omsg_fmt(w)
+    }
+}
+
+impl<T: OmsgDisplay + ?Sized> OmsgDisplay for &mut T {
+    #[inline]
+    fn omsg_fmt<W: OmsgWrite + ?Sized>(&self, w: &mut W) {
+        (**self).omsg_fmt(w)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fmt<T: OmsgDisplay + ?Sized>(value: &T) -> String {
+        let mut text = String::new();
+        value.omsg_fmt(&mut text);
+        text
+    }
+
+    #[test]
+    fn test_integers() {
+        assert_eq!(fmt(&0u8), "0");
+        assert_eq!(fmt(&u64::MAX), u64::MAX.to_string());
+        assert_eq!(fmt(&u128::MAX), u128::MAX.to_string());
+        assert_eq!(fmt(&i8::MIN), i8::MIN.to_string());
+        assert_eq!(fmt(&i64::MIN), i64::MIN.to_string());
+        assert_eq!(fmt(&i128::MIN), i128::MIN.to_string());
+        assert_eq!(fmt(&-42isize), "-42");
+    }
+
+    #[test]
+    fn test_pubkey() {
+        let key = Pubkey::new_unique();
+        assert_eq!(fmt(&key), key.to_string());
+    }
+
+    #[test]
+    fn test_other_types() {
+        assert_eq!(fmt(&true), "true");
+        assert_eq!(fmt(&'é'), "é");
+        assert_eq!(fmt("text"), "text");
+        assert_eq!(fmt(&&String::from("string")), "string");
+        let mut af = ArrForm::<8>::new();
+        "overflowing".omsg_fmt(&mut af);
+        assert!(af.is_truncated());
+        assert_eq!(fmt(&af), "overflow");
+    }
+}
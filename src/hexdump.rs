@@ -0,0 +1,277 @@
+//! `xxd` style dumps of account and instruction data, for debugging layout mismatches in
+//! deployed accounts. every row of 16 bytes is logged as its own line, showing the offset, the
+//! bytes in hex and their printable ascii characters, all formatted into one reused stack buffer.
+//!
+//! ```
+//! use omsg::omsg_hexdump;
+//! use omsg::testing::capture_logs;
+//!
+//! let data = b"omsg hexdump\x00\x01\x02\x03\xff";
+//! let logs = capture_logs(|| {
+//!     omsg_hexdump!("ix", &data);
+//! });
+//! assert_eq!(logs, vec![
+//!     "ix: 17 bytes, dumping 0..17",
+//!     "ix 00000000: 6f6d 7367 2068 6578 6475 6d70 0001 0203  omsg hexdump....",
+//!     "ix 00000010: ff                                       .",
+//! ]);
+//! ```
+//!
+//! at most [DEFAULT_MAX_ROWS] rows are logged unless another limit is given, as every line
+//! costs compute units and the log of a transaction is truncated after 10KB
+
+use crate::{ArrForm, OverflowPolicy};
+use core::cell::{Ref, RefMut};
+use core::ops::{Bound, Range, RangeBounds};
+use solana_program::account_info::AccountInfo;
+use solana_program::log::sol_log;
+
+/// the number of rows logged by `omsg_hexdump!` unless `max_rows` is given, 256 bytes of data
+pub const DEFAULT_MAX_ROWS: usize = 16;
+
+/// the number of bytes shown per row
+const BYTES_PER_ROW: usize = 16;
+
+/// the size of the buffer a row is formatted into, a row takes 67 bytes besides its label.
+/// longer labels are truncated
+const ROW_BUF_SIZE: usize = 160;
+
+/// logs a labelled hexdump of the bytes in `range` of the data, which can be a byte slice,
+/// array or vector, or an `AccountInfo`, whose data is borrowed for the duration of the dump.
+/// the range defaults to all of the data and is clamped to its length, and the offsets shown
+/// are relative to the start of the data. at most [hexdump::DEFAULT_MAX_ROWS] rows are logged
+/// unless `max_rows` is given.
+///
+/// returns the number of lines logged
+/// ```
+/// use omsg::omsg_hexdump;
+/// use solana_program::{account_info::AccountInfo, pubkey::Pubkey};
+///
+/// let (key, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+/// let (mut lamports, mut data) = (0, [0u8; 600]);
+/// let account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &owner, false, 0);
+///
+/// omsg_hexdump!("reserve", &account);
+/// omsg_hexdump!("reserve liquidity", &account, 8..72);
+/// omsg_hexdump!("reserve", &account, .., max_rows = 40);
+/// ```
+///
+/// [hexdump::DEFAULT_MAX_ROWS]: crate::hexdump::DEFAULT_MAX_ROWS
+#[macro_export]
+macro_rules! omsg_hexdump {
+    ($label:expr, $data:expr $(,)?) => {
+        $crate::omsg_hexdump!($label, $data, .., max_rows = $crate::hexdump::DEFAULT_MAX_ROWS)
+    };
+    ($label:expr, $data:expr, $range:expr $(,)?) => {
+        $crate::omsg_hexdump!($label, $data, $range, max_rows = $crate::hexdump::DEFAULT_MAX_ROWS)
+    };
+    ($label:expr, $data:expr, $range:expr, max_rows = $max_rows:expr $(,)?) => {
+        $crate::hexdump::HexdumpSource::log_hexdump(
+            &$data,
+            $label,
+            $crate::hexdump::to_range(&$range),
+            $max_rows,
+        )
+    };
+}
+
+/// data which `omsg_hexdump!` can dump
+pub trait HexdumpSource {
+    /// logs the bytes in `range` of the data, returning the number of lines logged
+    fn log_hexdump(&self, label: &str, range: Range<usize>, max_rows: usize) -> usize;
+}
+
+impl HexdumpSource for [u8] {
+    fn log_hexdump(&self, label: &str, range: Range<usize>, max_rows: usize) -> usize {
+        log_rows(label, self, range, max_rows)
+    }
+}
+
+impl<const N: usize> HexdumpSource for [u8; N] {
+    fn log_hexdump(&self, label: &str, range: Range<usize>, max_rows: usize) -> usize {
+        log_rows(label, self, range, max_rows)
+    }
+}
+
+impl HexdumpSource for Vec<u8> {
+    fn log_hexdump(&self, label: &str, range: Range<usize>, max_rows: usize) -> usize {
+        log_rows(label, self, range, max_rows)
+    }
+}
+
+impl HexdumpSource for AccountInfo<'_> {
+    fn log_hexdump(&self, label: &str, range: Range<usize>, max_rows: usize) -> usize {
+        match self.try_borrow_data() {
+            Ok(data) => log_rows(label, &data, range, max_rows),
+            Err(_) => {
+                let mut af = ArrForm::<ROW_BUF_SIZE>::with_policy(OverflowPolicy::TruncateWithEllipsis);
+                let _ = af.push_str(label);
+                let _ = af.push_str(": account data is mutably borrowed");
+                sol_log(af.as_str());
+                1
+            }
+        }
+    }
+}
+
+impl<T: HexdumpSource + ?Sized> HexdumpSource for &T {
+    fn log_hexdump(&self, label: &str, range: Range<usize>, max_rows: usize) -> usize {
+        (**self).log_hexdump(label, range, max_rows)
+    }
+}
+
+impl<T: HexdumpSource + ?Sized> HexdumpSource for &mut T {
+    fn log_hexdump(&self, label: &str, range: Range<usize>, max_rows: usize) -> usize {
+        (**self).log_hexdump(label, range, max_rows)
+    }
+}
+
+impl<T: HexdumpSource + ?Sized> HexdumpSource for Ref<'_, T> {
+    fn log_hexdump(&self, label: &str, range: Range<usize>, max_rows: usize) -> usize {
+        (**self).log_hexdump(label, range, max_rows)
+    }
+}
+
+impl<T: HexdumpSource + ?Sized> HexdumpSource for RefMut<'_, T> {
+    fn log_hexdump(&self, label: &str, range: Range<usize>, max_rows: usize) -> usize {
+        (**self).log_hexdump(label, range, max_rows)
+    }
+}
+
+/// converts the range given to `omsg_hexdump!` into offsets, with an unbounded end as
+/// `usize::MAX`
+#[doc(hidden)]
+pub fn to_range<R: RangeBounds<usize>>(range: &R) -> Range<usize> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.saturating_add(1),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => usize::MAX,
+    };
+    start..end
+}
+
+#[inline(never)]
+fn log_rows(label: &str, data: &[u8], range: Range<usize>, max_rows: usize) -> usize {
+    let end = range.end.min(data.len());
+    let start = range.start.min(end);
+    let mut af = ArrForm::<ROW_BUF_SIZE>::with_policy(OverflowPolicy::TruncateWithEllipsis);
+    let _ = af.push_str(label);
+    let _ = af.push_str(": ");
+    let _ = af.push_usize(data.len());
+    let _ = af.push_str(" bytes, dumping ");
+    let _ = af.push_usize(start);
+    let _ = af.push_str("..");
+    let _ = af.push_usize(end);
+    sol_log(af.as_str());
+
+    let mut lines = 1;
+    let mut rows = data[start..end].chunks(BYTES_PER_ROW);
+    for (offset, row) in (start..).step_by(BYTES_PER_ROW).zip(rows.by_ref().take(max_rows)) {
+        af.clear();
+        let _ = af.push_str(label);
+        let _ = af.push_str(" ");
+        push_row(&mut af, offset, row);
+        sol_log(af.as_str());
+        lines += 1;
+    }
+    if rows.len() > 0 {
+        let hidden: usize = rows.map(<[u8]>::len).sum();
+        af.clear();
+        let _ = af.push_str(label);
+        let _ = af.push_str(": ");
+        let _ = af.push_usize(hidden);
+        let _ = af.push_str(" more bytes not shown, raise max_rows to see them");
+        sol_log(af.as_str());
+        lines += 1;
+    }
+    lines
+}
+
+/// appends a row in the layout of `xxd`, `00000010: 0102 0304 ...  ascii`. the hex column of
+/// a partial row is padded so the ascii column lines up with the rows before it
+fn push_row<const BUF_SIZE: usize>(af: &mut ArrForm<BUF_SIZE>, offset: usize, row: &[u8]) {
+    let _ = af.push_hex(&(offset as u32).to_be_bytes());
+    let _ = af.push_str(":");
+    for group in 0..BYTES_PER_ROW / 2 {
+        let _ = af.push_str(" ");
+        let bytes = row.get(group * 2..).unwrap_or_default();
+        let bytes = &bytes[..bytes.len().min(2)];
+        let _ = af.push_hex(bytes);
+        for _ in bytes.len()..2 {
+            let _ = af.push_str("  ");
+        }
+    }
+    let _ = af.push_str("  ");
+    for &byte in row {
+        let _ = af.push_char(if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::capture_logs;
+    use solana_program::pubkey::Pubkey;
+
+    #[test]
+    fn test_rows() {
+        let data: Vec<u8> = (0..40).collect();
+        let logs = capture_logs(|| {
+            assert_eq!(crate::omsg_hexdump!("data", &data, 30..), 2);
+        });
+        assert_eq!(
+            logs,
+            vec![
+                "data: 40 bytes, dumping 30..40",
+                "data 0000001e: 1e1f 2021 2223 2425 2627                 .. !\"#$%&'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_max_rows() {
+        let data = [b'a'; 100];
+        let logs = capture_logs(|| {
+            assert_eq!(crate::omsg_hexdump!("data", data, ..=49, max_rows = 2), 4);
+        });
+        assert_eq!(logs.len(), 4);
+        assert_eq!(logs[2], "data 00000010: 6161 6161 6161 6161 6161 6161 6161 6161  aaaaaaaaaaaaaaaa");
+        assert_eq!(logs[3], "data: 18 more bytes not shown, raise max_rows to see them");
+    }
+
+    #[test]
+    fn test_out_of_bounds() {
+        let logs = capture_logs(|| {
+            crate::omsg_hexdump!("empty", &[0u8; 4], 10..20);
+        });
+        assert_eq!(logs, vec!["empty: 4 bytes, dumping 4..4"]);
+    }
+
+    #[test]
+    fn test_account_info() {
+        let (key, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut lamports, mut data) = (0, [0xffu8; 8]);
+        let account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &owner, false, 0);
+        let logs = capture_logs(|| {
+            crate::omsg_hexdump!("account", &account);
+            crate::omsg_hexdump!("borrowed", &account.data.borrow(), ..4);
+            let _guard = account.try_borrow_mut_data().unwrap();
+            crate::omsg_hexdump!("account", &account);
+        });
+        assert_eq!(
+            logs,
+            vec![
+                "account: 8 bytes, dumping 0..8",
+                "account 00000000: ffff ffff ffff ffff                      ........",
+                "borrowed: 8 bytes, dumping 0..4",
+                "borrowed 00000000: ffff ffff                                ....",
+                "account: account data is mutably borrowed",
+            ]
+        );
+    }
+}
@@ -0,0 +1,401 @@
+//! json lines without allocation. every log line is one compact json object, which log pipelines
+//! can parse with any json parser instead of a regex per message. strings are escaped, integers
+//! are written as numbers, and `Pubkey` and the other encodings are written as strings
+//! ```
+//! use omsg::json::JsonWriter;
+//! use omsg::ArrForm;
+//! use solana_program::pubkey::Pubkey;
+//!
+//! let mut af = ArrForm::<256>::new();
+//! let mut json = JsonWriter::new(&mut af).quote_large_ints(true);
+//! json.field("event", "deposit")
+//!     .field("reserve", &2u8)
+//!     .field("amount", &u64::MAX)
+//!     .field("user", &Pubkey::default())
+//!     .field("memo", &Some("say \"gm\"\n"));
+//! json.finish();
+//! assert_eq!(
+//!     af,
+//!     r#"{"event":"deposit","reserve":2,"amount":"18446744073709551615","user":"11111111111111111111111111111111","memo":"say \"gm\"\n"}"#
+//! );
+//! ```
+//!
+//! javascript parses numbers as doubles, which lose precision above 2^53. with
+//! [JsonWriter::quote_large_ints], or `quote_large_ints;` in front of the fields of `ojson!`,
+//! integers of 64 bits and more are written as strings
+
+use crate::amount::{FormattedAmount, TokenAmount};
+use crate::buckets::Logged;
+use crate::encoding::{OmsgPubkey, B58, B64, Hex};
+use crate::fast::{OmsgDisplay, OmsgWrite};
+use crate::{ArrForm, OverflowPolicy};
+use solana_program::pubkey::Pubkey;
+
+/// the size of the buffer `ojson!` formats a line into. fields which don't fit are left out, and
+/// the object ends with a `"truncated":true` field instead, so the line is still valid json
+pub const LINE_SIZE: usize = 512;
+
+/// the field added to an object whose fields didn't all fit
+const TRUNCATED_FIELD: &str = "\"truncated\":true";
+
+/// the room kept free after every field for `,"truncated":true}`, which is also the length of
+/// `{"truncated":true}` and so the smallest buffer a [JsonWriter] accepts
+const RESERVED: usize = ",".len() + TRUNCATED_FIELD.len() + "}".len();
+
+/// logs a json object with the given fields as one line, see [json](crate::json). the keys
+/// are identifiers and the values can be any type implementing [ToJson]. every value is
+/// evaluated exactly once, in the order given. fields preceded by `quote_large_ints;` write
+/// integers of 64 bits and more as strings.
+///
+/// returns a [Logged] with the number of bytes logged, whose path is
+/// [LogPath::Truncated](crate::LogPath::Truncated) if some fields didn't fit into
+/// [json::LINE_SIZE](crate::json::LINE_SIZE) bytes and were left out
+/// ```
+/// use omsg::ojson;
+/// use omsg::testing::capture_logs;
+///
+/// let logs = capture_logs(|| {
+///     ojson!(event = "deposit", amount = 123u64, reserve = 2u8);
+///     ojson!(quote_large_ints; event = "deposit", amount = 123u64);
+/// });
+/// assert_eq!(logs, vec![
+///     r#"{"event":"deposit","amount":123,"reserve":2}"#,
+///     r#"{"event":"deposit","amount":"123"}"#,
+/// ]);
+/// ```
+#[macro_export]
+macro_rules! ojson {
+    (quote_large_ints; $($key:ident = $value:expr),* $(,)?) => {
+        $crate::ojson!(@quote true; $($key = $value),*)
+    };
+    (@quote $quote:literal; $($key:ident = $value:expr),*) => {
+        $crate::__omsg_fields!($crate::json::log_json, [quote_large_ints = $quote] $($key = $value),*)
+    };
+    ($($key:ident = $value:expr),* $(,)?) => {
+        $crate::ojson!(@quote false; $($key = $value),*)
+    };
+}
+
+/// the entry point of `ojson!`, which logs the object `write` adds the fields to, kept out of
+/// line like [log_kv](crate::kv::log_kv)
+#[doc(hidden)]
+#[inline(never)]
+pub fn log_json(quote_large_ints: bool, write: &mut dyn FnMut(&mut JsonWriter<'_, LINE_SIZE>)) -> Logged {
+    let mut af = ArrForm::<LINE_SIZE>::new();
+    let mut json = JsonWriter::new(&mut af).quote_large_ints(quote_large_ints);
+    write(&mut json);
+    let truncated = json.is_truncated();
+    json.finish();
+    crate::buckets::log_line(&af, truncated)
+}
+
+/// writes a json object into an [ArrForm]. fields which don't fit are left out, along with
+/// every field after them, and [JsonWriter::finish] then adds a `"truncated":true` field, so
+/// the object stays valid json. the overflow policy of the buffer is restored once the writer
+/// is dropped
+pub struct JsonWriter<'a, const BUF_SIZE: usize> {
+    af: &'a mut ArrForm<BUF_SIZE>,
+    policy: OverflowPolicy,
+    empty: bool,
+    truncated: bool,
+    quote_large_ints: bool,
+}
+
+impl<'a, const BUF_SIZE: usize> JsonWriter<'a, BUF_SIZE> {
+    /// clears `af` and opens an object. fails to compile for buffers too small to hold
+    /// `{"truncated":true}`, so that every object the writer finishes is valid json
+    pub fn new(af: &'a mut ArrForm<BUF_SIZE>) -> Self {
+        const { assert!(BUF_SIZE >= RESERVED, "the buffer of a JsonWriter must hold at least 18 bytes") };
+        let policy = af.policy();
+        af.clear();
+        af.set_policy(OverflowPolicy::Truncate);
+        let _ = af.push_str("{");
+        JsonWriter { af, policy, empty: true, truncated: false, quote_large_ints: false }
+    }
+
+    /// writes integers of 64 bits and more as strings if `quote` is true
+    pub fn quote_large_ints(mut self, quote: bool) -> Self {
+        self.quote_large_ints = quote;
+        self
+    }
+
+    /// appends a field with the escaped `key`. a field which doesn't fit is removed again, and
+    /// no further fields are written
+    pub fn field<T: ToJson + ?Sized>(&mut self, key: &str, value: &T) -> &mut Self {
+        if self.truncated {
+            return self;
+        }
+        let start = self.af.len();
+        let _ = self.af.push_str(if self.empty { "\"" } else { ",\"" });
+        crate::escape::push_escaped(self.af, key);
+        let _ = self.af.push_str("\":");
+        value.to_json(self);
+        if self.af.is_truncated() || self.af.len() > BUF_SIZE.saturating_sub(RESERVED) {
+            // also clears the truncated flag of the buffer, so the object can still be closed
+            self.af.truncate(start);
+            self.truncated = true;
+        } else {
+            self.empty = false;
+        }
+        self
+    }
+
+    /// closes the object, after a `"truncated":true` field if any field was left out
+    pub fn finish(self) {
+        if self.truncated {
+            let _ = self.af.push_str(if self.empty { "" } else { "," });
+            let _ = self.af.push_str(TRUNCATED_FIELD);
+        }
+        let _ = self.af.push_str("}");
+    }
+
+    /// returns true if any field was left out because it didn't fit into the buffer
+    pub fn is_truncated(&self) -> bool {
+        self.truncated || self.af.is_truncated()
+    }
+
+    /// appends the text of `value` as an escaped string
+    pub fn string<T: OmsgDisplay + ?Sized>(&mut self, value: &T) {
+        let _ = self.af.push_str("\"");
+        value.omsg_fmt(&mut Escaped(&mut *self.af));
+        let _ = self.af.push_str("\"");
+    }
+
+    /// appends an integer as a number, or as a string if `large` and large integers are quoted
+    pub fn integer<T: OmsgDisplay + ?Sized>(&mut self, value: &T, large: bool) {
+        if large && self.quote_large_ints {
+            self.string(value);
+        } else {
+            value.omsg_fmt(&mut *self.af);
+        }
+    }
+
+    /// appends `true` or `false`
+    pub fn boolean(&mut self, value: bool) {
+        let _ = self.af.push_str(if value { "true" } else { "false" });
+    }
+
+    /// appends `null`
+    pub fn null(&mut self) {
+        let _ = self.af.push_str("null");
+    }
+}
+
+impl<const BUF_SIZE: usize> Drop for JsonWriter<'_, BUF_SIZE> {
+    fn drop(&mut self) {
+        self.af.set_policy(self.policy);
+    }
+}
+
+/// escapes the text pushed into it for a json string
+struct Escaped<'a, const BUF_SIZE: usize>(&'a mut ArrForm<BUF_SIZE>);
+
+impl<const BUF_SIZE: usize> OmsgWrite for Escaped<'_, BUF_SIZE> {
+    fn push_str(&mut self, s: &str) {
+        crate::escape::push_escaped(self.0, s)
+    }
+}
+
+/// a value of a json field, written by `ojson!` and [JsonWriter::field]
+pub trait ToJson {
+    fn to_json<const BUF_SIZE: usize>(&self, json: &mut JsonWriter<'_, BUF_SIZE>);
+}
+
+macro_rules! impl_to_json_integer {
+    ($($ty:ty => $large:literal),* $(,)?) => {
+        $(
+            impl ToJson for $ty {
+                fn to_json<const BUF_SIZE: usize>(&self, json: &mut JsonWriter<'_, BUF_SIZE>) {
+                    json.integer(self, $large)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_to_json_string {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl ToJson for $ty {
+                fn to_json<const BUF_SIZE: usize>(&self, json: &mut JsonWriter<'_, BUF_SIZE>) {
+                    json.string(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_to_json_integer! {
+    u8 => false,
+    u16 => false,
+    u32 => false,
+    i8 => false,
+    i16 => false,
+    i32 => false,
+    u64 => true,
+    i64 => true,
+    usize => true,
+    isize => true,
+    u128 => true,
+    i128 => true,
+}
+
+impl_to_json_string! {
+    str,
+    String,
+    char,
+    Pubkey,
+    OmsgPubkey<'_>,
+    TokenAmount,
+    FormattedAmount,
+    Hex<'_>,
+    B64<'_>,
+    B58<'_>,
+}
+
+impl ToJson for bool {
+    fn to_json<const BUF_SIZE: usize>(&self, json: &mut JsonWriter<'_, BUF_SIZE>) {
+        json.boolean(*self)
+    }
+}
+
+impl<const N: usize> ToJson for ArrForm<N> {
+    fn to_json<const BUF_SIZE: usize>(&self, json: &mut JsonWriter<'_, BUF_SIZE>) {
+        json.string(self)
+    }
+}
+
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json<const BUF_SIZE: usize>(&self, json: &mut JsonWriter<'_, BUF_SIZE>) {
+        match self {
+            Some(value) => value.to_json(json),
+            None => json.null(),
+        }
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for &T {
+    fn to_json<const BUF_SIZE: usize>(&self, json: &mut JsonWriter<'_, BUF_SIZE>) {
+        (**self).to_json(json)
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for &mut T {
+    fn to_json<const BUF_SIZE: usize>(&self, json: &mut JsonWriter<'_, BUF_SIZE>) {
+        (**self).to_json(json)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::capture_logs;
+    use crate::LogPath;
+
+    fn object(quote_large_ints: bool, write: impl FnOnce(&mut JsonWriter<'_, 512>)) -> serde_json::Value {
+        let mut af = ArrForm::<512>::new();
+        let mut json = JsonWriter::new(&mut af).quote_large_ints(quote_large_ints);
+        write(&mut json);
+        json.finish();
+        serde_json::from_str(af.as_str()).unwrap()
+    }
+
+    #[test]
+    fn test_escaping() {
+        let text = "quote \" backslash \\ newline \n tab \t bell \u{7} del \u{7f} unicode é🦀";
+        let value = object(false, |json| {
+            json.field("text", text).field("key \"quoted\"", &'\n');
+        });
+        assert_eq!(value["text"], text);
+        assert_eq!(value["key \"quoted\""], "\n");
+    }
+
+    #[test]
+    fn test_integers() {
+        let value = object(false, |json| {
+            json.field("u8", &u8::MAX).field("i64", &i64::MIN).field("u64", &u64::MAX).field("i32", &-7i32);
+        });
+        assert_eq!(value["u8"], 255);
+        assert_eq!(value["i64"], i64::MIN);
+        assert_eq!(value["u64"], u64::MAX);
+        assert_eq!(value["i32"], -7);
+
+        let value = object(true, |json| {
+            json.field("u32", &u32::MAX).field("u64", &5u64).field("i128", &i128::MIN);
+        });
+        assert_eq!(value["u32"], u32::MAX);
+        assert_eq!(value["u64"], "5");
+        assert_eq!(value["i128"], i128::MIN.to_string());
+    }
+
+    #[test]
+    fn test_other_values() {
+        let key = Pubkey::new_unique();
+        let value = object(false, |json| {
+            json.field("key", &key)
+                .field("short", &OmsgPubkey::short(&key))
+                .field("data", &Hex(&[1, 2]))
+                .field("amount", &TokenAmount::new(1_500_000, 6).trim_zeros())
+                .field("flag", &true)
+                .field("none", &None::<u8>);
+        });
+        assert_eq!(value["key"], key.to_string());
+        assert_eq!(value["short"], OmsgPubkey::short(&key).to_string());
+        assert_eq!(value["data"], "0102");
+        assert_eq!(value["amount"], "1.5");
+        assert_eq!(value["flag"], true);
+        assert!(value["none"].is_null());
+    }
+
+    #[test]
+    fn test_ojson() {
+        let user = Pubkey::new_unique();
+        let logs = capture_logs(|| {
+            let logged = crate::ojson!(event = "deposit", user = user, amount = 7u64,);
+            assert_eq!(logged.path, LogPath::Stack { bucket: LINE_SIZE });
+            crate::ojson!();
+        });
+        assert_eq!(logs, vec![format!(r#"{{"event":"deposit","user":"{}","amount":7}}"#, user), "{}".to_string()]);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let long = "\"".repeat(200);
+        let logs = capture_logs(|| {
+            let logged = crate::ojson!(event = "deposit", first = long, second = long, amount = 7u64);
+            assert_eq!(logged.path, LogPath::Truncated);
+            assert!(logged.len <= LINE_SIZE);
+            let logged = crate::ojson!(first = "x".repeat(LINE_SIZE));
+            assert_eq!(logged.path, LogPath::Truncated);
+        });
+        let value: serde_json::Value = serde_json::from_str(&logs[0]).unwrap();
+        assert_eq!(value["event"], "deposit");
+        assert_eq!(value["first"], long);
+        assert!(value.get("second").is_none());
+        assert!(value.get("amount").is_none());
+        assert_eq!(value["truncated"], true);
+        assert_eq!(logs[1], r#"{"truncated":true}"#);
+    }
+
+    #[test]
+    fn test_policy_restored() {
+        let mut af = ArrForm::<32>::new();
+        let mut json = JsonWriter::new(&mut af);
+        json.field("key", "x".repeat(32).as_str());
+        json.finish();
+        assert_eq!(af, r#"{"truncated":true}"#);
+        assert_eq!(af.policy(), OverflowPolicy::Error);
+    }
+
+    #[test]
+    fn test_smallest_buffer() {
+        // the size named by the assertion in `JsonWriter::new`
+        assert_eq!(RESERVED, 18);
+        let mut af = ArrForm::<RESERVED>::new();
+        let mut json = JsonWriter::new(&mut af);
+        json.field("key", &1u8);
+        json.finish();
+        assert_eq!(af, r#"{"truncated":true}"#);
+        assert!(serde_json::from_str::<serde_json::Value>(af.as_str()).is_ok());
+    }
+}
@@ -0,0 +1,196 @@
+//! structured `key=value` log lines in the style of logfmt, for log scrapers and indexers. a
+//! line starts with the name of the event, followed by its fields in the order they are given
+//! ```text
+//! deposit amount=123 user=4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T reserve=2
+//! ```
+//!
+//! values are formatted through [OmsgDisplay], so the output doesn't depend on format specs
+//! chosen at each call site. a value is put in double quotes if it is empty or contains a space,
+//! `=`, `"` or a control character, and within quotes `"` and `\` are escaped with a backslash,
+//! and control characters as `\n`, `\r`, `\t` or `\u00XX`
+//! ```
+//! use omsg::kv::KvWriter;
+//! use omsg::ArrForm;
+//!
+//! let mut af = ArrForm::<128>::new();
+//! KvWriter::new(&mut af, "liquidate")
+//!     .field("obligation", &7u64)
+//!     .field("reason", "ltv above \"threshold\"")
+//!     .field("note", "");
+//! assert_eq!(af, r#"liquidate obligation=7 reason="ltv above \"threshold\"" note="""#);
+//! ```
+
+use crate::buckets::Logged;
+use crate::fast::OmsgDisplay;
+use crate::{ArrForm, OverflowPolicy};
+
+/// the size of the buffer `okv!` formats a line into. longer lines are truncated
+pub const LINE_SIZE: usize = 512;
+
+/// the size of the buffer a single value is formatted into before it is quoted. longer values
+/// are truncated
+const VALUE_SIZE: usize = 256;
+
+/// logs a `key=value` line for an event, see [kv](crate::kv). the keys are identifiers and the
+/// values can be any type implementing [OmsgDisplay], such as integers, strings, `Pubkey`,
+/// [TokenAmount](crate::TokenAmount) or [Hex](crate::encoding::Hex). every value is evaluated
+/// exactly once, in the order given.
+///
+/// returns a [Logged] with the number of bytes logged, whose path is
+/// [LogPath::Truncated](crate::LogPath::Truncated) if the line didn't fit into
+/// [kv::LINE_SIZE](crate::kv::LINE_SIZE) bytes
+/// ```
+/// use omsg::okv;
+/// use omsg::testing::capture_logs;
+/// use solana_program::pubkey::Pubkey;
+///
+/// let user = Pubkey::new_from_array([7; 32]);
+/// let logs = capture_logs(|| {
+///     okv!("deposit", amount = 123u64, user = user, reserve = 2u8);
+/// });
+/// assert_eq!(logs, vec![format!("deposit amount=123 user={} reserve=2", user)]);
+/// ```
+#[macro_export]
+macro_rules! okv {
+    ($event:expr $(, $key:ident = $value:expr)* $(,)?) => {
+        $crate::__omsg_fields!($crate::kv::log_kv, [event = $event] $($key = $value),*)
+    };
+}
+
+/// writes a `key=value` line into an [ArrForm]. text that doesn't fit is truncated with a
+/// "…" marker, and the overflow policy of the buffer is restored once the writer is dropped
+pub struct KvWriter<'a, const BUF_SIZE: usize> {
+    af: &'a mut ArrForm<BUF_SIZE>,
+    policy: OverflowPolicy,
+    truncated_value: bool,
+}
+
+impl<'a, const BUF_SIZE: usize> KvWriter<'a, BUF_SIZE> {
+    /// clears `af` and starts a line for `event`
+    pub fn new(af: &'a mut ArrForm<BUF_SIZE>, event: &str) -> Self {
+        let policy = af.policy();
+        af.clear();
+        af.set_policy(OverflowPolicy::TruncateWithEllipsis);
+        let _ = af.push_str(event);
+        KvWriter { af, policy, truncated_value: false }
+    }
+
+    /// appends ` key=value`, quoting the value if needed. values longer than 256 bytes are
+    /// truncated
+    pub fn field<T: OmsgDisplay + ?Sized>(&mut self, key: &str, value: &T) -> &mut Self {
+        let mut text = ArrForm::<VALUE_SIZE>::with_policy(OverflowPolicy::TruncateWithEllipsis);
+        value.omsg_fmt(&mut text);
+        self.truncated_value |= text.is_truncated();
+        self.push_field(key, text.as_str());
+        self
+    }
+
+    /// returns true if the line didn't fit into the buffer, or one of its values was truncated
+    pub fn is_truncated(&self) -> bool {
+        self.truncated_value || self.af.is_truncated()
+    }
+
+    fn push_field(&mut self, key: &str, value: &str) {
+        let af = &mut *self.af;
+        let _ = af.push_str(" ");
+        let _ = af.push_str(key);
+        let _ = af.push_str("=");
+        let needs_quotes = value.is_empty() || value.chars().any(|c| matches!(c, ' ' | '=' | '"') || c.is_control());
+        if !needs_quotes {
+            let _ = af.push_str(value);
+            return;
+        }
+        let _ = af.push_str("\"");
+        crate::escape::push_escaped(af, value);
+        let _ = af.push_str("\"");
+    }
+}
+
+impl<const BUF_SIZE: usize> Drop for KvWriter<'_, BUF_SIZE> {
+    fn drop(&mut self) {
+        self.af.set_policy(self.policy);
+    }
+}
+
+/// the entry point of `okv!`, which logs the line `write` adds the fields of `event` to. like
+/// every entry point it is not inlined, so the line buffer is only part of its own stack frame
+/// instead of the frame of every function with an `okv!` call site
+#[doc(hidden)]
+#[inline(never)]
+pub fn log_kv(event: &str, write: &mut dyn FnMut(&mut KvWriter<'_, LINE_SIZE>)) -> Logged {
+    let mut af = ArrForm::<LINE_SIZE>::new();
+    let mut kv = KvWriter::new(&mut af, event);
+    write(&mut kv);
+    let truncated = kv.is_truncated();
+    drop(kv);
+    crate::buckets::log_line(&af, truncated)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::capture_logs;
+    use crate::{LogPath, TokenAmount};
+    use solana_program::pubkey::Pubkey;
+
+    fn line<T: OmsgDisplay + ?Sized>(value: &T) -> String {
+        let mut af = ArrForm::<128>::new();
+        KvWriter::new(&mut af, "event").field("key", value);
+        af.as_str().to_string()
+    }
+
+    #[test]
+    fn test_quoting() {
+        assert_eq!(line("plain"), "event key=plain");
+        assert_eq!(line("two words"), r#"event key="two words""#);
+        assert_eq!(line("a=b"), r#"event key="a=b""#);
+        assert_eq!(line(""), r#"event key="""#);
+        assert_eq!(line(r"back\slash"), r"event key=back\slash");
+        assert_eq!(line("say \"hi\"\\"), r#"event key="say \"hi\"\\""#);
+        assert_eq!(line("line\nbreak\t\u{1}\u{85}"), r#"event key="line\nbreak\t\u0001\u0085""#);
+        assert_eq!(line("café"), "event key=café");
+    }
+
+    #[test]
+    fn test_okv() {
+        let user = Pubkey::new_unique();
+        let amount = TokenAmount::new(1_500_000, 6);
+        let mut evaluated = 0;
+        let logs = capture_logs(|| {
+            let logged = crate::okv!("deposit", amount = amount, user = user, reserve = { evaluated += 1; 2u8 },);
+            assert_eq!(logged.path, LogPath::Stack { bucket: LINE_SIZE });
+            assert_eq!(crate::okv!("refresh").len, "refresh".len());
+        });
+        assert_eq!(evaluated, 1);
+        assert_eq!(logs, vec![format!("deposit amount=1.500000 user={} reserve=2", user), "refresh".to_string()]);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let long = "x".repeat(LINE_SIZE);
+        let logs = capture_logs(|| {
+            let logged = crate::okv!("event", first = long.as_str(), second = long.as_str());
+            assert_eq!(logged.path, LogPath::Truncated);
+            assert!(logged.len <= LINE_SIZE);
+        });
+        assert!(logs[0].ends_with('…'));
+    }
+
+    #[test]
+    fn test_truncated_value() {
+        let long = "x".repeat(2 * VALUE_SIZE);
+        let logs = capture_logs(|| {
+            let logged = crate::okv!("event", long = long.as_str(), after = 1u8);
+            assert_eq!(logged.path, LogPath::Truncated);
+        });
+        assert!(logs[0].len() < LINE_SIZE);
+        assert!(logs[0].ends_with("… after=1"));
+    }
+
+    #[test]
+    fn test_policy_restored() {
+        let mut af = ArrForm::<8>::new();
+        KvWriter::new(&mut af, "event").field("key", "a long value");
+        assert_eq!(af.policy(), OverflowPolicy::Error);
+    }
+}
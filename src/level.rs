@@ -0,0 +1,190 @@
+//! log levels for the `omsg_error!`, `omsg_warn!`, `omsg_info!`, `omsg_debug!` and
+//! `omsg_trace_level!` macros. the maximum level is chosen at compile time through cargo
+//! features, and messages above it are removed from the compiled program entirely.
+//!
+//! * `max-level-off`, `max-level-error`, `max-level-warn`, `max-level-info`,
+//!   `max-level-debug` and `max-level-trace` set the maximum level for all builds
+//! * the `release-max-level-*` variants of these features only apply to builds without
+//!   debug assertions, such as the release builds deployed on chain, and take precedence
+//!   over the `max-level-*` features in those builds
+//!
+//! when multiple features of the same kind are enabled the most restrictive one wins.
+//! without any of these features every level is logged.
+
+/// the level of a log message, ordered from most to least severe
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(usize)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+/// the maximum level that is logged, `Off` disables logging through the level macros
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(usize)]
+pub enum LevelFilter {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    /// the tag each message of this level is prefixed with
+    pub const fn tag(self) -> &'static str {
+        match self {
+            Level::Error => "[ERROR] ",
+            Level::Warn => "[WARN] ",
+            Level::Info => "[INFO] ",
+            Level::Debug => "[DEBUG] ",
+            Level::Trace => "[TRACE] ",
+        }
+    }
+
+    /// returns true if messages of this level are compiled in
+    pub const fn enabled(self) -> bool {
+        self as usize <= STATIC_MAX_LEVEL as usize
+    }
+}
+
+/// the maximum level compiled in, as selected by the `max-level-*` and
+/// `release-max-level-*` features
+pub const STATIC_MAX_LEVEL: LevelFilter = if cfg!(debug_assertions) {
+    MAX_LEVEL
+} else {
+    RELEASE_MAX_LEVEL
+};
+
+const MAX_LEVEL: LevelFilter = if cfg!(feature = "max-level-off") {
+    LevelFilter::Off
+} else if cfg!(feature = "max-level-error") {
+    LevelFilter::Error
+} else if cfg!(feature = "max-level-warn") {
+    LevelFilter::Warn
+} else if cfg!(feature = "max-level-info") {
+    LevelFilter::Info
+} else if cfg!(feature = "max-level-debug") {
+    LevelFilter::Debug
+} else {
+    LevelFilter::Trace
+};
+
+const RELEASE_MAX_LEVEL: LevelFilter = if cfg!(feature = "release-max-level-off") {
+    LevelFilter::Off
+} else if cfg!(feature = "release-max-level-error") {
+    LevelFilter::Error
+} else if cfg!(feature = "release-max-level-warn") {
+    LevelFilter::Warn
+} else if cfg!(feature = "release-max-level-info") {
+    LevelFilter::Info
+} else if cfg!(feature = "release-max-level-debug") {
+    LevelFilter::Debug
+} else if cfg!(feature = "release-max-level-trace") {
+    LevelFilter::Trace
+} else {
+    MAX_LEVEL
+};
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_level_ordering() {
+        assert!(Level::Error < Level::Warn);
+        assert!(Level::Debug < Level::Trace);
+        assert!((Level::Error as usize) > (LevelFilter::Off as usize));
+        assert_eq!(Level::Info as usize, LevelFilter::Info as usize);
+    }
+
+    /// asserts that exactly the levels up to `filter` are enabled
+    fn assert_max_level(filter: LevelFilter) {
+        assert_eq!(STATIC_MAX_LEVEL, filter);
+        for level in [Level::Error, Level::Warn, Level::Info, Level::Debug, Level::Trace] {
+            assert_eq!(level.enabled(), level as usize <= filter as usize);
+        }
+    }
+
+    /// generates a test for each feature, which applies in builds matching `$applies` unless
+    /// a more restrictive feature of the same kind is enabled as well. run the tests with each
+    /// of the features, and with `--release` for the `release-max-level-*` features
+    macro_rules! max_level_tests {
+        ($applies:meta; $($test:ident: $feature:literal $(, unless $stricter:literal)* => $filter:ident;)*) => {
+            $(
+                #[test]
+                #[cfg(all($applies, feature = $feature, not(any($(feature = $stricter),*))))]
+                fn $test() {
+                    assert_max_level(LevelFilter::$filter);
+                }
+            )*
+        };
+    }
+
+    max_level_tests! {
+        any(
+            debug_assertions,
+            not(any(
+                feature = "release-max-level-off",
+                feature = "release-max-level-error",
+                feature = "release-max-level-warn",
+                feature = "release-max-level-info",
+                feature = "release-max-level-debug",
+                feature = "release-max-level-trace",
+            )),
+        );
+        test_max_level_off: "max-level-off" => Off;
+        test_max_level_error: "max-level-error", unless "max-level-off" => Error;
+        test_max_level_warn: "max-level-warn", unless "max-level-off", unless "max-level-error" => Warn;
+        test_max_level_info: "max-level-info", unless "max-level-off", unless "max-level-error",
+            unless "max-level-warn" => Info;
+        test_max_level_debug: "max-level-debug", unless "max-level-off", unless "max-level-error",
+            unless "max-level-warn", unless "max-level-info" => Debug;
+        test_max_level_trace: "max-level-trace", unless "max-level-off", unless "max-level-error",
+            unless "max-level-warn", unless "max-level-info", unless "max-level-debug" => Trace;
+    }
+
+    max_level_tests! {
+        not(debug_assertions);
+        test_release_max_level_off: "release-max-level-off" => Off;
+        test_release_max_level_error: "release-max-level-error", unless "release-max-level-off" => Error;
+        test_release_max_level_warn: "release-max-level-warn", unless "release-max-level-off",
+            unless "release-max-level-error" => Warn;
+        test_release_max_level_info: "release-max-level-info", unless "release-max-level-off",
+            unless "release-max-level-error", unless "release-max-level-warn" => Info;
+        test_release_max_level_debug: "release-max-level-debug", unless "release-max-level-off",
+            unless "release-max-level-error", unless "release-max-level-warn",
+            unless "release-max-level-info" => Debug;
+        test_release_max_level_trace: "release-max-level-trace", unless "release-max-level-off",
+            unless "release-max-level-error", unless "release-max-level-warn",
+            unless "release-max-level-info", unless "release-max-level-debug" => Trace;
+    }
+
+    #[test]
+    #[cfg(not(any(
+        feature = "max-level-off",
+        feature = "max-level-error",
+        feature = "max-level-warn",
+        feature = "max-level-info",
+        feature = "max-level-debug",
+        feature = "max-level-trace",
+        all(
+            not(debug_assertions),
+            any(
+                feature = "release-max-level-off",
+                feature = "release-max-level-error",
+                feature = "release-max-level-warn",
+                feature = "release-max-level-info",
+                feature = "release-max-level-debug",
+                feature = "release-max-level-trace",
+            ),
+        ),
+    )))]
+    fn test_default_max_level() {
+        assert_max_level(LevelFilter::Trace);
+    }
+}
@@ -6,75 +6,553 @@
 //! estimates seem to indicate that each log msg which includes string formatting using `omsg`
 //! should save around ~200 compute units.
 
+pub mod amount;
 pub mod arrform;
-pub use arrform::ArrForm;
+pub mod buckets;
+pub mod encoding;
+mod escape;
+pub mod fast;
+pub mod hexdump;
+pub mod json;
+pub mod kv;
+pub mod level;
+pub mod literal;
+pub mod num;
+pub mod sizing;
+#[cfg(not(target_os = "solana"))]
+pub mod testing;
+pub mod trace;
+pub use amount::{DecimalStyle, TokenAmount};
+pub use arrform::{ArrForm, ArrFormError, OverflowPolicy};
+pub use buckets::{LogPath, Logged};
+pub use num::IntFormat;
 
+/// logs a message like `omsg!`, but formats it through [fast::OmsgDisplay] instead of
+/// `core::fmt`. the format string is parsed at compile time into direct writes of its text and
+/// arguments, so only plain `{}`, `{0}` and `{name}` placeholders are supported, and every
+/// argument must implement [fast::OmsgDisplay]. like `omsg!` it returns a [Logged]
+/// ```
+/// use omsg::omsg_fast;
+/// use solana_program::pubkey::Pubkey;
+///
+/// let reserve = Pubkey::new_unique();
+/// let amount = 42u64;
+/// omsg_fast!("deposited {amount} into {} at {{slot {}}}", reserve, 1000u64);
+/// ```
+#[macro_export]
+macro_rules! omsg_fast {
+    ($($args:tt)+) => {
+        $crate::__private::__omsg_fast!([$crate] $($args)+)
+    };
+}
+
+/// formats a message into an `ArrForm` of the given size like `arrform!`, with the placeholders
+/// of `omsg_fast!`. text that doesn't fit is dropped, check `is_truncated` to find out
+/// ```
+/// use omsg::oformat;
+///
+/// let af = oformat!(32, "reserve {} healthy: {}", 3u8, true);
+/// assert_eq!(af, "reserve 3 healthy: true");
+/// ```
+#[macro_export]
+macro_rules! oformat {
+    ($($args:tt)+) => {
+        $crate::__private::__oformat!([$crate] $($args)+)
+    };
+}
+
+/// items used by the exported macros, not part of the public api
+#[doc(hidden)]
+pub mod __private {
+    pub use omsg_macros::{__oformat, __omsg_fast};
+
+    /// lets `omsg_flush!` accept both an `ArrForm` and a mutable reference to one
+    pub trait Flush {
+        fn __omsg_flush(&mut self) -> Option<crate::Logged>;
+    }
+
+    impl<const BUF_SIZE: usize> Flush for crate::ArrForm<BUF_SIZE> {
+        fn __omsg_flush(&mut self) -> Option<crate::Logged> {
+            if self.is_empty() {
+                return None;
+            }
+            let logged = crate::buckets::log_line(self, self.is_truncated());
+            self.clear();
+            Some(logged)
+        }
+    }
+}
+
+/// returns an upper bound for the length of the message produced by formatting the given
+/// format string and arguments, or `usize::MAX` if no upper bound is known. the bound is the
+/// length of the format string plus the maximum display width of every argument, as given
+/// by [sizing::MaxDisplayLen]
 #[macro_export]
 macro_rules! sum {
-    // this delcares an exrpession i think :shrug:
-    // todo(): explain this more
-    ($($args:expr),*) => {{
-        let result = 0;
+    ($fmt:expr $(, $args:expr)* $(,)?) => {{
+        #[allow(unused_imports)]
+        use $crate::sizing::{KnownSize as _, UnknownSize as _};
+        const FMT_BOUND: usize = $crate::sizing::format_str_bound($fmt, <[&str]>::len(&[$(::core::stringify!($args)),*]));
+        let result = FMT_BOUND;
         $(
-            // combine the size of each value 
-            let result = result + std::mem::size_of_val(&$args);
+            // combine the maximum display width of each value
+            let result = result.saturating_add((&$crate::sizing::SizeOf(&$args)).omsg_size());
         )*
-        // return the size of all arguments
+        // return the bound of the whole message
         result
     }}
 }
 
 /// an optimized form of the `msg!` macro, which attempts to utilizes stack based formatting
 /// of strings instead of heap based formatting where possible, attempting to optimize the stack
-/// that is used. in the even of a message requiring larger than 768 stack bytes, regular msg formatting is used
+/// that is used. the formatted stack buffer is handed directly to `sol_log`, so the stack path
+/// performs no heap allocations. if the message overflows the bucket chosen from its size bound,
+/// it is formatted again into the next larger bucket. in the even of a message requiring larger
+/// than 768 stack bytes, regular heap based formatting is used.
+///
+/// messages without arguments or placeholders skip formatting entirely, their `{{` and `}}`
+/// escapes are resolved at compile time and the text is passed straight to `sol_log`.
+///
+/// each argument is evaluated exactly once, the same as with `msg!`. the macro is an expression
+/// returning a [Logged] with the path the message took and the number of bytes logged, so it
+/// can be used as a match arm or closure body
+/// ```
+/// use omsg::{omsg, LogPath};
+///
+/// let logged = omsg!("deposited {} into reserve {}", 42u64, 3u8);
+/// assert_eq!(logged.path, LogPath::Stack { bucket: 64 });
+/// assert_eq!(logged.len, "deposited 42 into reserve 3".len());
+/// ```
 #[macro_export]
 macro_rules! omsg {
     ($($args:tt)+) => {
-        let input_sizes = sum!($($args)*);
-        match input_sizes {
-            s if s <= 768 && s > 512 => msg!("{}", arrform!(768, $($args)*).as_str()),
-            s if s <= 512 && s > 256 => msg!("{}", arrform!(512, $($args)*).as_str()),
-            s if s <= 256 && s > 128 => msg!("{}", arrform!(256, $($args)*).as_str()),
-            s if s <= 128 && s > 64 => msg!("{}", arrform!(128, $($args)*).as_str()),
-            s if s <= 64 && s > 32 => msg!("{}", arrform!(64, $($args)*).as_str()),
-            s if s <= 32 && s > 0 => msg!("{}", arrform!(32, $($args)*).as_str()),
-            _ => msg!("{}", format!($($args)*)),
-        }
+        $crate::__omsg!(@start [log_args, ""] $($args)+)
     };
 }
 
-/// similar to `omsg!` except it adds tracing information (file and line number). if the combined file and line number
-/// results in a byte size > 128, this will cause a run time error
+/// similar to `omsg!` except that a message too long for the largest bucket is truncated to it
+/// and ends with a "…" marker, instead of being formatted on the heap. use it on paths such as
+/// liquidations, where a long message must not run the program out of heap memory. the
+/// returned [Logged] has the path [LogPath::Truncated] if the message was cut off
+/// ```
+/// use omsg::{omsg_truncate, LogPath};
+///
+/// let obligations = [u64::MAX; 50];
+/// let logged = omsg_truncate!("liquidating obligations {:?}", obligations);
+/// assert_eq!(logged.path, LogPath::Truncated);
+/// assert_eq!(logged.len, 768);
+/// ```
+#[macro_export]
+macro_rules! omsg_truncate {
+    ($($args:tt)+) => {
+        $crate::__omsg!(@start [log_args_truncated, ""] $($args)+)
+    };
+}
+
+/// similar to `omsg!` except it prefixes the message with tracing information (file name and
+/// line number), such as `[processor.rs:42] `. the prefix is built at compile time, so tracing
+/// costs no more than the bytes of the prefix
 #[macro_export]
 macro_rules! omsg_trace {
     ($($args:tt)+) => {
-        let file_name = std::path::Path::new(file!()).file_name().unwrap().to_string_lossy();
-        let file_info = arrform!(128, "{}:{}", file_name, line!());
-        let input_sizes = sum!($($args)*);
-        match input_sizes  {
-            s if s <= 768 && s > 512 => msg!("[{}] {}", file_info.as_str(), arrform!(768, $($args)*).as_str()),
-            s if s <= 512 && s > 256 => msg!("[{}] {}", file_info.as_str(), arrform!(512, $($args)*).as_str()),
-            s if s <= 256 && s > 128 => msg!("[{}] {}", file_info.as_str(), arrform!(256, $($args)*).as_str()),
-            s if s <= 128 && s > 64 => msg!("[{}] {}", file_info.as_str(),  arrform!(128, $($args)*).as_str()),
-            s if s <= 64 && s > 32 => msg!("[{}] {}",  file_info.as_str(), arrform!(64, $($args)*).as_str()),
-            s if s <= 32 && s > 0 => msg!("[{}] {}", file_info.as_str(), arrform!(32, $($args)*).as_str()),
-            _ => msg!("[{}] {}", file_info.as_str(),  format!($($args)*)),
+        $crate::__omsg!(@start [log_args, $crate::__trace_prefix!(file)] $($args)+)
+    };
+}
+
+/// similar to `omsg_trace!` except the prefix also contains the column, such as `[processor.rs:42:9] `
+#[macro_export]
+macro_rules! omsg_trace_column {
+    ($($args:tt)+) => {
+        $crate::__omsg!(@start [log_args, $crate::__trace_prefix!(column)] $($args)+)
+    };
+}
+
+/// similar to `omsg_trace!` except the prefix contains the module path instead of the
+/// file name, such as `[lending::processor:42] `
+#[macro_export]
+macro_rules! omsg_trace_module {
+    ($($args:tt)+) => {
+        $crate::__omsg!(@start [log_args, $crate::__trace_prefix!(module)] $($args)+)
+    };
+}
+
+/// logs a message at the error level, prefixed with `[ERROR] `. see [level] for how levels
+/// are removed at compile time. like every level macro it evaluates to `Some(Logged)`, or to
+/// `None` if the level is disabled
+#[macro_export]
+macro_rules! omsg_error {
+    ($($args:tt)+) => {
+        $crate::__omsg_level!(Error, $($args)+)
+    };
+}
+
+/// logs a message at the warn level, prefixed with `[WARN] `
+#[macro_export]
+macro_rules! omsg_warn {
+    ($($args:tt)+) => {
+        $crate::__omsg_level!(Warn, $($args)+)
+    };
+}
+
+/// logs a message at the info level, prefixed with `[INFO] `
+#[macro_export]
+macro_rules! omsg_info {
+    ($($args:tt)+) => {
+        $crate::__omsg_level!(Info, $($args)+)
+    };
+}
+
+/// logs a message at the debug level, prefixed with `[DEBUG] `
+#[macro_export]
+macro_rules! omsg_debug {
+    ($($args:tt)+) => {
+        $crate::__omsg_level!(Debug, $($args)+)
+    };
+}
+
+/// logs a message at the trace level, prefixed with `[TRACE] `. not to be confused with
+/// `omsg_trace!`, which prefixes messages with their file and line number
+#[macro_export]
+macro_rules! omsg_trace_level {
+    ($($args:tt)+) => {
+        $crate::__omsg_level!(Trace, $($args)+)
+    };
+}
+
+/// implementation detail of the level macros. the level check is evaluated in a const block,
+/// so disabled levels are removed from the compiled program along with their arguments, even
+/// without optimizations. evaluates to `None` for disabled levels
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __omsg_level {
+    ($level:ident, $($args:tt)+) => {
+        if const { $crate::level::Level::$level.enabled() } {
+            ::core::option::Option::Some($crate::__omsg!(@start [log_args, $crate::level::Level::$level.tag()] $($args)+))
+        } else {
+            ::core::option::Option::None
+        }
+    };
+}
+
+/// implementation detail of `okv!` and `ojson!`. the arguments in brackets and the values of
+/// the fields are evaluated at the call site, in the order given, so that a value can use `?` or
+/// `return`. the bound values are then passed to the `$log` entry point along with a closure,
+/// which only writes the fields into the writer it is given. each key doubles as the name of
+/// the temporary holding its value
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __omsg_fields {
+    ($log:path, [$($name:ident = $arg:expr),*] $($key:ident = $value:expr),*) => {{
+        #[allow(non_snake_case, unused_variables)]
+        let logged = match ($($arg,)* $(&$value,)*) {
+            ($($name,)* $($key,)*) => $log($($name,)* &mut |writer| {
+                $(
+                    writer.field(::core::stringify!($key), $key);
+                )*
+            }),
+        };
+        logged
+    }};
+}
+
+/// appends formatted text to an existing `ArrForm` without resetting it, so that a message can
+/// be built in pieces and logged once with `omsg_flush!`. text that doesn't fit is handled by
+/// the overflow policy of the buffer, check `is_truncated` to find out if anything was lost
+/// ```
+/// use omsg::{omsg_flush, omsg_write, ArrForm};
+///
+/// let mut af = ArrForm::<128>::new();
+/// omsg_write!(af, "refreshed obligations");
+/// for obligation in [3, 7, 11] {
+///     omsg_write!(af, " {}", obligation);
+/// }
+/// // logs "refreshed obligations 3 7 11"
+/// omsg_flush!(af);
+/// ```
+#[macro_export]
+macro_rules! omsg_write {
+    ($af:expr, $($args:tt)+) => {{
+        let _ = $af.append(::core::format_args!($($args)+));
+    }};
+}
+
+/// logs the text of an `ArrForm` built with `omsg_write!` and clears the buffer for reuse.
+/// nothing is logged if the buffer is empty, in which case `None` is returned. the path of the
+/// returned [Logged] is [LogPath::Truncated] if text didn't fit into the buffer
+#[macro_export]
+macro_rules! omsg_flush {
+    ($af:expr) => {{
+        use $crate::__private::Flush as _;
+        $af.__omsg_flush()
+    }};
+}
+
+/// implementation detail of the `omsg!` family of macros. every argument is bound to a
+/// temporary exactly once, and the temporaries are then used both to size the message
+/// and to format it. the `[...]` context holds the entry point in [buckets] the message is
+/// logged through, followed by the `&'static str` prefix of the message
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __omsg {
+    (@start [$($ctx:tt)*] $fmt:expr $(, $($args:tt)*)?) => {
+        $crate::__omsg!(@bind [$($ctx)*] $fmt; [] []; $($($args)*)?)
+    };
+    // every argument is bound
+    (@bind [$($ctx:tt)*] $fmt:expr; [$($pos:tt)*] [$($named:tt)*];) => {
+        $crate::__omsg!(@emit [$($ctx)*] $fmt; [$($pos)*] [$($named)*])
+    };
+    // each recursion introduces a new `arg`, which macro hygiene keeps distinct
+    (@bind [$($ctx:tt)*] $fmt:expr; [$($pos:tt)*] [$($named:tt)*]; $name:ident = $arg:expr $(, $($rest:tt)*)?) => {
+        match &$arg {
+            arg => $crate::__omsg!(@bind [$($ctx)*] $fmt; [$($pos)*] [$($named)* $name = arg]; $($($rest)*)?),
         }
     };
+    (@bind [$($ctx:tt)*] $fmt:expr; [$($pos:tt)*] [$($named:tt)*]; $arg:expr $(, $($rest:tt)*)?) => {
+        match &$arg {
+            arg => $crate::__omsg!(@bind [$($ctx)*] $fmt; [$($pos)* arg] [$($named)*]; $($($rest)*)?),
+        }
+    };
+    // without arguments, a format string without placeholders is logged as a constant
+    (@emit [$log:ident, $prefix:expr] $fmt:expr; [] []) => {{
+        const PREFIX: &str = $prefix;
+        const LITERAL: bool = $crate::literal::is_literal($fmt);
+        if LITERAL {
+            const LEN: usize = $crate::literal::unescaped_len(PREFIX, $fmt);
+            const BYTES: [u8; LEN] = $crate::literal::unescape::<LEN>(PREFIX, $fmt);
+            const MESSAGE: &str = $crate::trace::as_str(&BYTES);
+            $crate::buckets::log_literal(MESSAGE)
+        } else {
+            $crate::buckets::$log(
+                PREFIX,
+                $crate::sum!($fmt).saturating_add(PREFIX.len()),
+                ::core::format_args!($fmt),
+            )
+        }
+    }};
+    (@emit [$log:ident, $prefix:expr] $fmt:expr; [$($pos:ident)*] [$($name:ident = $named:ident)*]) => {{
+        const PREFIX: &str = $prefix;
+        $crate::buckets::$log(
+            PREFIX,
+            $crate::sum!($fmt $(, $pos)* $(, $named)*).saturating_add(PREFIX.len()),
+            ::core::format_args!($fmt $(, $pos)* $(, $name = $named)*),
+        )
+    }};
 }
 
 
 #[cfg(test)]
 mod test {
-    use super::*;
-    use solana_program::msg;
+    use crate::testing::capture_logs;
     #[test]
     fn test_omsg() {
-        omsg!("abc too {}", "yooo");
-        omsg_trace!("abc too {}", "yoooo");
+        let (logs, line) = (
+            capture_logs(|| {
+                omsg!("abc too {}", "yooo");
+                omsg_trace!("abc too {}", "yoooo");
+                omsg_trace_column!("abc too {}", "yoooo");
+                omsg_trace_module!("abc too {}", "yoooo");
+            }),
+            line!(),
+        );
+        assert_eq!(
+            logs,
+            vec![
+                "abc too yooo".to_string(),
+                format!("[lib.rs:{}] abc too yoooo", line - 4),
+                format!("[lib.rs:{}:17] abc too yoooo", line - 3),
+                format!("[omsg::test:{}] abc too yoooo", line - 2),
+            ]
+        );
+    }
+    #[test]
+    fn test_omsg_truncate() {
+        use crate::LogPath;
+        let long = "x".repeat(1000);
+        let logs = capture_logs(|| {
+            assert_eq!(omsg_truncate!("liquidating").path, LogPath::Literal);
+            assert_eq!(omsg_truncate!("liquidating {}", 7u8).path, LogPath::Stack { bucket: 32 });
+            assert_eq!(omsg_truncate!("liquidating {}", long).path, LogPath::Truncated);
+        });
+        assert_eq!(logs[..2], ["liquidating", "liquidating 7"]);
+        assert!(logs[2].starts_with("liquidating xxx"));
+        assert!(logs[2].ends_with('…'));
+        assert_eq!(logs[2].len(), 768);
+    }
+    #[test]
+    fn test_omsg_levels() {
+        use crate::level::Level;
+        let amount = 42u64;
+        let logs = capture_logs(|| {
+            omsg_error!("liquidation failed for {}", amount);
+            omsg_warn!("obligation {} is unhealthy", amount);
+            omsg_info!("refreshed reserve");
+            omsg_debug!("amount {amount}");
+            omsg_trace_level!("{} {}", amount, "trace");
+        });
+        // which levels are enabled depends on the level features, see `level::test`
+        let expected: Vec<&str> = [
+            (Level::Error, "[ERROR] liquidation failed for 42"),
+            (Level::Warn, "[WARN] obligation 42 is unhealthy"),
+            (Level::Info, "[INFO] refreshed reserve"),
+            (Level::Debug, "[DEBUG] amount 42"),
+            (Level::Trace, "[TRACE] 42 trace"),
+        ]
+        .into_iter()
+        .filter(|(level, _)| level.enabled())
+        .map(|(_, line)| line)
+        .collect();
+        assert_eq!(logs, expected);
+    }
+    #[test]
+    fn test_disabled_levels_skip_arguments() {
+        use crate::level::Level;
+        let mut evaluated = false;
+        let logged = omsg_trace_level!("{}", {
+            evaluated = true;
+            1u8
+        });
+        assert_eq!(logged.is_some(), Level::Trace.enabled());
+        assert_eq!(evaluated, Level::Trace.enabled());
     }
     #[test]
     fn test_size_ofs() {
-        println!("{}", sum!("y", "o", "bbbbbb"));
+        assert_eq!(sum!("{}{}", "o", "bbbbbb"), 4 + 1 + 6);
+        assert_eq!(sum!("liquidity {}", 42u64), 12 + 20);
+        assert_eq!(sum!("{}", 4.2f64), usize::MAX);
+    }
+    #[test]
+    fn test_omsg_long_messages() {
+        let long = "x".repeat(300);
+        let logs = capture_logs(|| {
+            // used to be estimated at 16 bytes, overflowing the 32 byte bucket
+            omsg!("{}", long.as_str());
+            omsg!("reserve {} refreshed, liquidity {}", 3u8, u64::MAX);
+            omsg_trace!("reserve {} refreshed, liquidity {}", 3u8, u64::MAX);
+        });
+        assert_eq!(logs[0], long);
+        assert_eq!(logs[1], format!("reserve 3 refreshed, liquidity {}", u64::MAX));
+        assert!(logs[2].ends_with(&logs[1]));
+    }
+    #[test]
+    fn test_omsg_evaluates_arguments_once() {
+        struct Counter(u64);
+        impl Counter {
+            fn next(&mut self) -> u64 {
+                self.0 += 1;
+                self.0
+            }
+        }
+        let mut counter = Counter(0);
+        omsg!("{}", counter.next());
+        assert_eq!(counter.0, 1);
+        omsg!("{} {}", counter.next(), counter.next());
+        assert_eq!(counter.0, 3);
+        omsg!("{next}", next = counter.next(),);
+        assert_eq!(counter.0, 4);
+        omsg_trace!("{} {}", counter.next(), "trace");
+        assert_eq!(counter.0, 5);
+        let mut evaluations = 0;
+        omsg!("{:?}", {
+            evaluations += 1;
+            [evaluations; 4]
+        });
+        assert_eq!(evaluations, 1);
+    }
+    #[test]
+    fn test_omsg_argument_forms() {
+        let amount = 42u64;
+        let logs = capture_logs(|| {
+            omsg!("amount {amount}");
+            omsg!("amount {0} {0}", amount);
+            omsg!("amount {amount} {:>8}", "x", amount = amount + 1);
+            omsg!(concat!("amount ", "{}"), amount);
+        });
+        assert_eq!(logs, vec!["amount 42", "amount 42 42", "amount 43        x", "amount 42"]);
+    }
+    #[test]
+    fn test_omsg_write_and_flush() {
+        let mut af = crate::ArrForm::<32>::new();
+        let logs = capture_logs(|| {
+            omsg_write!(af, "obligations:");
+            for obligation in [3, 7, 11] {
+                omsg_write!(af, " {}", obligation);
+            }
+            assert_eq!(omsg_flush!(af).unwrap().path, crate::LogPath::Stack { bucket: 32 });
+            // the buffer is cleared, so flushing again logs nothing
+            assert_eq!(omsg_flush!(af), None);
+            omsg_write!(af, "{}", "x".repeat(40));
+            let logged = omsg_flush!(&mut af).unwrap();
+            assert_eq!(logged, crate::Logged { path: crate::LogPath::Truncated, len: 32 });
+        });
+        assert_eq!(logs, vec!["obligations: 3 7 11".to_string(), "x".repeat(32)]);
+    }
+    #[test]
+    fn test_omsg_as_expression() {
+        use crate::{LogPath, Logged};
+        let logs = capture_logs(|| {
+            let logged = match Some(7u8) {
+                Some(reserve) => omsg!("reserve {}", reserve),
+                None => omsg!("no reserve"),
+            };
+            assert_eq!(logged, Logged { path: LogPath::Stack { bucket: 32 }, len: 9 });
+            let log = |amount: u64| omsg_trace!("amount {}", amount);
+            assert!(log(1).is_stack());
+            let logged: Vec<Logged> = [1u64, 2].iter().map(|v| omsg!("{}", v)).collect();
+            assert_eq!(logged.len(), 2);
+            let logged = omsg!("{}", "x".repeat(1000));
+            assert_eq!(logged.path, LogPath::Heap);
+            let error_enabled = crate::level::Level::Error.enabled();
+            assert_eq!(omsg_error!("failed").map(|logged| logged.len), error_enabled.then_some(14));
+            let mut af = crate::ArrForm::<32>::new();
+            assert_eq!(omsg_flush!(af), None);
+            omsg_write!(af, "flushed");
+            assert_eq!(omsg_flush!(af).map(|logged| logged.len), Some(7));
+        });
+        assert_eq!(logs.len(), 6 + crate::level::Level::Error.enabled() as usize);
     }
-}
\ No newline at end of file
+    #[test]
+    fn test_omsg_literals() {
+        use crate::level::Level;
+        use crate::LogPath;
+        let logs = capture_logs(|| {
+            assert_eq!(omsg!("refreshing reserve").path, LogPath::Literal);
+            assert_eq!(omsg!("escaped {{braces}}").len, 16);
+            let info = omsg_info!(concat!("refreshing ", "obligation")).map(|logged| logged.path);
+            assert_eq!(info, Level::Info.enabled().then_some(LogPath::Literal));
+            // inline captures are placeholders without arguments
+            let amount = 7u8;
+            assert_eq!(omsg!("amount {amount}").path, LogPath::Stack { bucket: 32 });
+        });
+        let mut expected = vec!["refreshing reserve", "escaped {braces}", "[INFO] refreshing obligation", "amount 7"];
+        if !Level::Info.enabled() {
+            expected.remove(2);
+        }
+        assert_eq!(logs, expected);
+    }
+    #[test]
+    fn test_omsg_fast() {
+        use crate::LogPath;
+        use solana_program::pubkey::Pubkey;
+        let reserve = Pubkey::new_unique();
+        let amount = 42u64;
+        let logs = capture_logs(|| {
+            assert_eq!(omsg_fast!("refreshing {{reserve}}").path, LogPath::Literal);
+            omsg_fast!("deposited {} into {}", amount, reserve);
+            omsg_fast!("{amount} {0} {name} {}", -7i8, name = true);
+            let logged = omsg_fast!("{}", "x".repeat(100));
+            assert_eq!(logged, crate::Logged { path: LogPath::Stack { bucket: 128 }, len: 100 });
+        });
+        assert_eq!(
+            logs,
+            vec![
+                "refreshing {reserve}".to_string(),
+                format!("deposited 42 into {}", reserve),
+                "42 -7 true -7".to_string(),
+                "x".repeat(100),
+            ]
+        );
+        let af = oformat!(16, "amount {} of {}", u128::MAX, amount);
+        assert!(af.is_truncated());
+        assert_eq!(af, "amount 340282366");
+    }
+}
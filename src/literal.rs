@@ -0,0 +1,92 @@
+//! compile time handling of format strings without placeholders. a message such as
+//! `omsg!("refreshing reserve")` has nothing to format, so its `{{` and `}}` escapes are
+//! resolved during constant evaluation and the resulting `&'static str` is handed directly to
+//! `sol_log`, skipping `core::fmt` entirely.
+
+/// returns true if `fmt` contains no placeholders, only literal text and `{{` / `}}` escapes
+pub const fn is_literal(fmt: &str) -> bool {
+    let bytes = fmt.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'{' || bytes[i] == b'}' {
+            if i + 1 < bytes.len() && bytes[i + 1] == bytes[i] {
+                i += 2;
+                continue;
+            }
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// returns the length of `prefix` followed by `fmt` with its escapes resolved
+#[doc(hidden)]
+pub const fn unescaped_len(prefix: &str, fmt: &str) -> usize {
+    let bytes = fmt.as_bytes();
+    let mut len = prefix.len();
+    let mut i = 0;
+    while i < bytes.len() {
+        if is_escape(bytes, i) {
+            i += 1;
+        }
+        len += 1;
+        i += 1;
+    }
+    len
+}
+
+/// writes `prefix` followed by `fmt` with its escapes resolved into an array, `LEN` must be the
+/// result of [unescaped_len]
+#[doc(hidden)]
+pub const fn unescape<const LEN: usize>(prefix: &str, fmt: &str) -> [u8; LEN] {
+    let mut out = [0u8; LEN];
+    let prefix = prefix.as_bytes();
+    let mut used = 0;
+    while used < prefix.len() {
+        out[used] = prefix[used];
+        used += 1;
+    }
+    let bytes = fmt.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if is_escape(bytes, i) {
+            i += 1;
+        }
+        out[used] = bytes[i];
+        used += 1;
+        i += 1;
+    }
+    assert!(used == LEN, "LEN must be the unescaped length");
+    out
+}
+
+/// returns true if `bytes[i]` starts a `{{` or `}}` escape
+const fn is_escape(bytes: &[u8], i: usize) -> bool {
+    (bytes[i] == b'{' || bytes[i] == b'}') && i + 1 < bytes.len() && bytes[i + 1] == bytes[i]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_literal() {
+        assert!(is_literal("refreshing reserve"));
+        assert!(is_literal("escaped {{}} braces"));
+        assert!(is_literal(""));
+        assert!(!is_literal("amount {}"));
+        assert!(!is_literal("amount {amount}"));
+        assert!(!is_literal("unbalanced }"));
+        assert!(!is_literal("{{{}}}"));
+    }
+
+    #[test]
+    fn test_unescape() {
+        const LEN: usize = unescaped_len("[INFO] ", "set {{a}} to }}{{");
+        assert_eq!(LEN, 20);
+        let bytes = unescape::<LEN>("[INFO] ", "set {{a}} to }}{{");
+        assert_eq!(&bytes, b"[INFO] set {a} to }{");
+        assert_eq!(unescaped_len("", "plain"), 5);
+    }
+}
@@ -0,0 +1,274 @@
+//! decimal formatting of integers without `core::fmt`. the digits are produced two at a time
+//! from a lookup table, and 128 bit integers are split into 64 bit chunks first, as 128 bit
+//! division is emulated in software on chain and `core::fmt` performs one per digit.
+//!
+//! ```
+//! use omsg::{ArrForm, IntFormat};
+//!
+//! let mut af = ArrForm::<64>::new();
+//! af.push_str("deposited ").unwrap();
+//! af.push_u64(1_500_000).unwrap();
+//! af.push_str(" into reserve ").unwrap();
+//! af.push_u8_with(7, IntFormat::new().width(3).zero_pad()).unwrap();
+//! af.push_str(", total ").unwrap();
+//! af.push_u128_with(1_234_567_890, IntFormat::new().separator(',')).unwrap();
+//! assert_eq!(af, "deposited 1500000 into reserve 007, total 1,234,567,890");
+//! ```
+
+use crate::ArrForm;
+use core::fmt;
+use core::str::from_utf8_unchecked;
+
+/// how the `push_*_with` methods of [ArrForm] lay out an integer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct IntFormat {
+    /// the minimum number of characters written, shorter numbers are padded on the left
+    pub width: usize,
+    /// pad with zeros after the sign instead of with spaces before it
+    pub zero_pad: bool,
+    /// inserted between groups of three digits, such as `1,234,567`
+    pub separator: Option<char>,
+}
+
+impl IntFormat {
+    /// the layout of the plain `push_*` methods, without padding or separators
+    pub const fn new() -> Self {
+        IntFormat { width: 0, zero_pad: false, separator: None }
+    }
+
+    /// pads the number to at least `width` characters
+    pub const fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// pads the number with zeros instead of spaces
+    pub const fn zero_pad(mut self) -> Self {
+        self.zero_pad = true;
+        self
+    }
+
+    /// groups the digits by three with `separator`
+    pub const fn separator(mut self, separator: char) -> Self {
+        self.separator = Some(separator);
+        self
+    }
+}
+
+/// every two digit number from "00" to "99"
+const DIGIT_PAIRS: &[u8; 200] = b"\
+    0001020304050607080910111213141516171819\
+    2021222324252627282930313233343536373839\
+    4041424344454647484950515253545556575859\
+    6061626364656667686970717273747576777879\
+    8081828384858687888990919293949596979899";
+
+/// 10^19, the largest power of ten below `u64::MAX`
+const U64_CHUNK: u128 = 10_000_000_000_000_000_000;
+
+/// writes the decimal digits of `value` to the end of `out`, returning how many were written
+fn write_digits(mut value: u64, out: &mut [u8]) -> usize {
+    let mut start = out.len();
+    while value >= 100 {
+        let pair = (value % 100) as usize * 2;
+        value /= 100;
+        start -= 2;
+        out[start..start + 2].copy_from_slice(&DIGIT_PAIRS[pair..pair + 2]);
+    }
+    if value >= 10 {
+        let pair = value as usize * 2;
+        start -= 2;
+        out[start..start + 2].copy_from_slice(&DIGIT_PAIRS[pair..pair + 2]);
+    } else {
+        start -= 1;
+        out[start] = b'0' + value as u8;
+    }
+    out.len() - start
+}
+
+/// returns the decimal digits of `value`, written to the end of `buf`
+pub(crate) fn u64_digits(value: u64, buf: &mut [u8; 20]) -> &str {
+    let len = write_digits(value, buf);
+    // only ascii digits were written
+    unsafe { from_utf8_unchecked(&buf[buf.len() - len..]) }
+}
+
+/// returns the decimal digits of `value`, written to the end of `buf`
+pub(crate) fn u128_digits(mut value: u128, buf: &mut [u8; 39]) -> &str {
+    let mut end = buf.len();
+    // peel off chunks of 19 digits until the rest fits into 64 bits
+    while value > u64::MAX as u128 {
+        let chunk = (value % U64_CHUNK) as u64;
+        value /= U64_CHUNK;
+        let len = write_digits(chunk, &mut buf[end - 19..end]);
+        buf[end - 19..end - len].fill(b'0');
+        end -= 19;
+    }
+    let len = write_digits(value as u64, &mut buf[..end]);
+    // only ascii digits were written
+    unsafe { from_utf8_unchecked(&buf[end - len..]) }
+}
+
+impl<const BUF_SIZE: usize> ArrForm<BUF_SIZE> {
+    /// appends `digits` laid out according to `format`, preceded by a minus sign if `negative`
+    fn push_int(&mut self, negative: bool, digits: &str, format: IntFormat) -> fmt::Result {
+        let separators = match format.separator {
+            Some(_) => (digits.len() - 1) / 3,
+            None => 0,
+        };
+        let padding = format.width.saturating_sub(negative as usize + digits.len() + separators);
+        if !format.zero_pad {
+            self.push_repeated(' ', padding)?;
+        }
+        if negative {
+            self.push_str("-")?;
+        }
+        if format.zero_pad {
+            self.push_repeated('0', padding)?;
+        }
+        match format.separator {
+            Some(separator) => {
+                // the first group holds the digits left over by the groups of three
+                let mut group = digits.len() - separators * 3;
+                self.push_str(&digits[..group])?;
+                while group < digits.len() {
+                    self.push_char(separator)?;
+                    self.push_str(&digits[group..group + 3])?;
+                    group += 3;
+                }
+                Ok(())
+            }
+            None => self.push_str(digits),
+        }
+    }
+
+    fn push_repeated(&mut self, c: char, count: usize) -> fmt::Result {
+        for _ in 0..count {
+            self.push_char(c)?;
+        }
+        Ok(())
+    }
+}
+
+macro_rules! impl_push_unsigned {
+    ($($ty:ty: $push:ident, $push_with:ident => $digits:ident, $buf:expr;)*) => {
+        impl<const BUF_SIZE: usize> ArrForm<BUF_SIZE> {
+            $(
+                #[doc = concat!("appends the decimal digits of a `", stringify!($ty), "`")]
+                pub fn $push(&mut self, value: $ty) -> fmt::Result {
+                    self.push_str($digits(value as _, &mut [0; $buf]))
+                }
+
+                #[doc = concat!("appends a `", stringify!($ty), "` laid out according to `format`")]
+                pub fn $push_with(&mut self, value: $ty, format: IntFormat) -> fmt::Result {
+                    self.push_int(false, $digits(value as _, &mut [0; $buf]), format)
+                }
+            )*
+        }
+    };
+}
+
+macro_rules! impl_push_signed {
+    ($($ty:ty: $push:ident, $push_with:ident => $digits:ident, $buf:expr;)*) => {
+        impl<const BUF_SIZE: usize> ArrForm<BUF_SIZE> {
+            $(
+                #[doc = concat!("appends the decimal digits of an `", stringify!($ty), "`, preceded by a minus sign if negative")]
+                pub fn $push(&mut self, value: $ty) -> fmt::Result {
+                    self.$push_with(value, IntFormat::new())
+                }
+
+                #[doc = concat!("appends an `", stringify!($ty), "` laid out according to `format`")]
+                pub fn $push_with(&mut self, value: $ty, format: IntFormat) -> fmt::Result {
+                    self.push_int(value < 0, $digits(value.unsigned_abs() as _, &mut [0; $buf]), format)
+                }
+            )*
+        }
+    };
+}
+
+impl_push_unsigned! {
+    u8: push_u8, push_u8_with => u64_digits, 20;
+    u16: push_u16, push_u16_with => u64_digits, 20;
+    u32: push_u32, push_u32_with => u64_digits, 20;
+    u64: push_u64, push_u64_with => u64_digits, 20;
+    usize: push_usize, push_usize_with => u64_digits, 20;
+    u128: push_u128, push_u128_with => u128_digits, 39;
+}
+
+impl_push_signed! {
+    i8: push_i8, push_i8_with => u64_digits, 20;
+    i16: push_i16, push_i16_with => u64_digits, 20;
+    i32: push_i32, push_i32_with => u64_digits, 20;
+    i64: push_i64, push_i64_with => u64_digits, 20;
+    isize: push_isize, push_isize_with => u64_digits, 20;
+    i128: push_i128, push_i128_with => u128_digits, 39;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn digits_u128(value: u128) -> String {
+        u128_digits(value, &mut [0; 39]).to_string()
+    }
+
+    #[test]
+    fn test_digits() {
+        for value in [0, 9, 10, 99, 100, 101, 999, 1000, u64::MAX] {
+            assert_eq!(u64_digits(value, &mut [0; 20]), value.to_string());
+        }
+        for value in [0, u64::MAX as u128, u64::MAX as u128 + 1, U64_CHUNK, U64_CHUNK * U64_CHUNK, u128::MAX] {
+            assert_eq!(digits_u128(value), value.to_string());
+        }
+    }
+
+    #[test]
+    fn test_push_ints() {
+        let mut af = ArrForm::<128>::new();
+        af.push_u8(255).unwrap();
+        af.push_char(' ').unwrap();
+        af.push_i8(i8::MIN).unwrap();
+        af.push_char(' ').unwrap();
+        af.push_i128(i128::MIN).unwrap();
+        af.push_char(' ').unwrap();
+        af.push_usize(0).unwrap();
+        assert_eq!(af.as_str(), format!("255 -128 {} 0", i128::MIN));
+    }
+
+    #[test]
+    fn test_push_int_formats() {
+        let fmt = |value: i64, format: IntFormat| {
+            let mut af = ArrForm::<64>::new();
+            af.push_i64_with(value, format).unwrap();
+            af.as_str().to_string()
+        };
+        assert_eq!(fmt(42, IntFormat::new().width(6)), "    42");
+        assert_eq!(fmt(-42, IntFormat::new().width(6)), "   -42");
+        assert_eq!(fmt(-42, IntFormat::new().width(6).zero_pad()), "-00042");
+        assert_eq!(fmt(123456, IntFormat::new().width(3)), "123456");
+        assert_eq!(fmt(1234567, IntFormat::new().separator(',')), "1,234,567");
+        assert_eq!(fmt(-123456, IntFormat::new().separator('_')), "-123_456");
+        assert_eq!(fmt(12, IntFormat::new().separator(',')), "12");
+        assert_eq!(fmt(1234, IntFormat::new().separator('’').width(7)), "  1’234");
+    }
+
+    #[test]
+    fn test_push_int_overflow() {
+        let mut af = ArrForm::<4>::new();
+        assert!(af.push_u32(123456).is_err());
+        assert_eq!(af, "1234");
+        assert!(af.is_truncated());
+    }
+
+    proptest! {
+        #[test]
+        #[cfg_attr(miri, ignore)]
+        fn test_matches_display(value: u128, signed: i64) {
+            prop_assert_eq!(digits_u128(value), value.to_string());
+            let mut af = ArrForm::<32>::new();
+            af.push_i64(signed).unwrap();
+            prop_assert_eq!(af.as_str(), signed.to_string());
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! upper bounds for the formatted length of an `omsg!` message, used to pick the smallest
+//! `ArrForm` bucket that is guaranteed to hold the message. the bound is the length of the
+//! format string itself plus any explicit widths, plus the maximum display width of every
+//! argument. whenever a message can't be bounded (unknown argument types, non `Display`
+//! formatting traits, arguments referenced by position or name) the bound is `usize::MAX`,
+//! in which case the message is formatted into every bucket in turn, starting with the
+//! smallest, and only falls back to the heap if it overflows the largest one.
+
+use solana_program::pubkey::Pubkey;
+
+/// the maximum number of bytes the `Display` implementation of a type can produce.
+///
+/// implement this for your own types to allow `omsg!` to format them on the stack
+/// ```
+/// use omsg::sizing::MaxDisplayLen;
+///
+/// struct ReserveIndex(u8);
+///
+/// impl MaxDisplayLen for ReserveIndex {
+///     fn max_display_len(&self) -> usize {
+///         // "reserve#" followed by at most 3 digits
+///         8 + 3
+///     }
+/// }
+/// ```
+pub trait MaxDisplayLen {
+    fn max_display_len(&self) -> usize;
+}
+
+macro_rules! impl_max_display_len {
+    ($($ty:ty => $max:expr),* $(,)?) => {
+        $(
+            impl MaxDisplayLen for $ty {
+                #[inline(always)]
+                fn max_display_len(&self) -> usize {
+                    $max
+                }
+            }
+        )*
+    };
+}
+
+impl_max_display_len! {
+    u8 => 3,
+    u16 => 5,
+    u32 => 10,
+    u64 => 20,
+    u128 => 39,
+    usize => decimal_width(usize::MAX as u128, false),
+    i8 => 4,
+    i16 => 6,
+    i32 => 11,
+    i64 => 20,
+    i128 => 40,
+    isize => decimal_width(isize::MAX as u128, true),
+    bool => 5,
+    char => 4,
+    // base58 encoding of 32 bytes
+    Pubkey => 44,
+}
+
+impl MaxDisplayLen for str {
+    #[inline(always)]
+    fn max_display_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl MaxDisplayLen for String {
+    #[inline(always)]
+    fn max_display_len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<const BUF_SIZE: usize> MaxDisplayLen for crate::ArrForm<BUF_SIZE> {
+    #[inline(always)]
+    fn max_display_len(&self) -> usize {
+        self.as_str().len()
+    }
+}
+
+impl<T: MaxDisplayLen + ?Sized> MaxDisplayLen for &T {
+    #[inline(always)]
+    fn max_display_len(&self) -> usize {
+        (**self).max_display_len()
+    }
+}
+
+impl<T: MaxDisplayLen + ?Sized> MaxDisplayLen for &mut T {
+    #[inline(always)]
+    fn max_display_len(&self) -> usize {
+        (**self).max_display_len()
+    }
+}
+
+/// number of decimal digits (plus an optional sign) needed to display `max`
+const fn decimal_width(mut max: u128, signed: bool) -> usize {
+    let mut width = if signed { 2 } else { 1 };
+    while max >= 10 {
+        max /= 10;
+        width += 1;
+    }
+    width
+}
+
+/// wraps an argument of `omsg!` so that its size can be looked up through autoref
+/// specialization: types implementing [MaxDisplayLen] resolve to [KnownSize], every
+/// other type resolves to [UnknownSize]
+#[doc(hidden)]
+pub struct SizeOf<'a, T: ?Sized>(pub &'a T);
+
+#[doc(hidden)]
+pub trait KnownSize {
+    fn omsg_size(&self) -> usize;
+}
+
+impl<T: MaxDisplayLen + ?Sized> KnownSize for SizeOf<'_, T> {
+    #[inline(always)]
+    fn omsg_size(&self) -> usize {
+        self.0.max_display_len()
+    }
+}
+
+#[doc(hidden)]
+pub trait UnknownSize {
+    fn omsg_size(&self) -> usize;
+}
+
+impl<T: ?Sized> UnknownSize for &SizeOf<'_, T> {
+    #[inline(always)]
+    fn omsg_size(&self) -> usize {
+        usize::MAX
+    }
+}
+
+/// returns an upper bound for the number of bytes the format string `fmt` contributes to the
+/// formatted output when it is given `args` positional arguments, or `usize::MAX` if the
+/// output of the format string can't be bounded by the display widths of its arguments
+pub const fn format_str_bound(fmt: &str, args: usize) -> usize {
+    let bytes = fmt.as_bytes();
+    // every literal byte, including the placeholders themselves, is counted
+    let mut bound = bytes.len();
+    let mut consumed_args = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' if i + 1 < bytes.len() && bytes[i + 1] == b'{' => i += 2,
+            b'}' if i + 1 < bytes.len() && bytes[i + 1] == b'}' => i += 2,
+            b'{' => {
+                i += 1;
+                // an explicit argument index or name can reference an argument more than once
+                if i < bytes.len() && bytes[i] != b':' && bytes[i] != b'}' {
+                    return usize::MAX;
+                }
+                consumed_args += 1;
+                if i < bytes.len() && bytes[i] == b':' {
+                    i += 1;
+                    // optional fill character followed by an alignment
+                    let fill_len = utf8_char_len(bytes, i);
+                    if i + fill_len < bytes.len() && is_align(bytes[i + fill_len]) {
+                        i += fill_len + 1;
+                    } else if i < bytes.len() && is_align(bytes[i]) {
+                        i += 1;
+                    }
+                    if i < bytes.len() && (bytes[i] == b'+' || bytes[i] == b'-') {
+                        i += 1;
+                    }
+                    // the alternate flag only changes non `Display` output
+                    if i < bytes.len() && bytes[i] == b'#' {
+                        return usize::MAX;
+                    }
+                    if i < bytes.len() && bytes[i] == b'0' {
+                        i += 1;
+                    }
+                    let mut width = 0;
+                    while i < bytes.len() && bytes[i].is_ascii_digit() {
+                        width = width * 10 + (bytes[i] - b'0') as usize;
+                        i += 1;
+                    }
+                    if i < bytes.len() && bytes[i] == b'$' {
+                        return usize::MAX;
+                    }
+                    bound += width;
+                    // precision never increases the output of bounded types
+                    if i < bytes.len() && bytes[i] == b'.' {
+                        i += 1;
+                        if i < bytes.len() && bytes[i] == b'*' {
+                            consumed_args += 1;
+                            i += 1;
+                        }
+                        while i < bytes.len() && bytes[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                        if i < bytes.len() && bytes[i] == b'$' {
+                            return usize::MAX;
+                        }
+                    }
+                }
+                // any formatting trait other than `Display` is not covered by `MaxDisplayLen`
+                if i >= bytes.len() || bytes[i] != b'}' {
+                    return usize::MAX;
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    if consumed_args != args {
+        return usize::MAX;
+    }
+    bound
+}
+
+const fn is_align(b: u8) -> bool {
+    b == b'<' || b == b'^' || b == b'>'
+}
+
+const fn utf8_char_len(bytes: &[u8], i: usize) -> usize {
+    if i >= bytes.len() {
+        return 0;
+    }
+    match bytes[i] {
+        b if b < 0x80 => 1,
+        b if b >= 0xf0 => 4,
+        b if b >= 0xe0 => 3,
+        _ => 2,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_integer_widths() {
+        assert_eq!(u64::MAX.to_string().len(), 0u64.max_display_len());
+        assert_eq!(i64::MIN.to_string().len(), 0i64.max_display_len());
+        assert_eq!(u128::MAX.to_string().len(), 0u128.max_display_len());
+        assert_eq!(i128::MIN.to_string().len(), 0i128.max_display_len());
+        assert_eq!(usize::MAX.to_string().len(), 0usize.max_display_len());
+        assert_eq!(isize::MIN.to_string().len(), 0isize.max_display_len());
+        assert_eq!(Pubkey::new_from_array([255; 32]).to_string().len(), Pubkey::default().max_display_len());
+    }
+
+    #[test]
+    fn test_format_str_bound() {
+        assert_eq!(format_str_bound("refreshing reserve", 0), 18);
+        assert_eq!(format_str_bound("escaped {{}}", 0), 12);
+        assert_eq!(format_str_bound("amount {}", 1), 9);
+        assert_eq!(format_str_bound("amount {:>12}", 1), 13 + 12);
+        assert_eq!(format_str_bound("amount {:*^8.2}", 1), 15 + 8);
+        assert_eq!(format_str_bound("{:.*}", 2), 5);
+        // placeholders and arguments don't line up
+        assert_eq!(format_str_bound("amount {}", 0), usize::MAX);
+        assert_eq!(format_str_bound("amount {amount}", 0), usize::MAX);
+        assert_eq!(format_str_bound("amount {0} {0}", 1), usize::MAX);
+        // widths taken from arguments and non display traits
+        assert_eq!(format_str_bound("amount {:1$}", 2), usize::MAX);
+        assert_eq!(format_str_bound("amount {:?}", 1), usize::MAX);
+        assert_eq!(format_str_bound("amount {:#x}", 1), usize::MAX);
+    }
+
+    #[test]
+    // the explicit borrow is what selects between the known and unknown size impls
+    #[allow(clippy::needless_borrow)]
+    fn test_unknown_sizes() {
+        struct Opaque;
+        assert_eq!((&SizeOf(&Opaque)).omsg_size(), usize::MAX);
+        assert_eq!((&SizeOf(&1.5f64)).omsg_size(), usize::MAX);
+        assert_eq!((&SizeOf(&"abc")).omsg_size(), 3);
+        assert_eq!((&SizeOf(&7u8)).omsg_size(), 3);
+    }
+}
@@ -0,0 +1,205 @@
+//! helpers for asserting on log output in native unit tests, without running a validator.
+//!
+//! [capture_logs] installs a `SyscallStubs` implementation the first time it is called, which
+//! records every `sol_log` line emitted by the calling thread while a capture is active. logs
+//! of other threads, and logs emitted outside of a capture, are forwarded to the stubs that were
+//! installed before, so tests running in parallel don't observe each other's logs.
+//!
+//! ```
+//! use omsg::{assert_log_contains, assert_logged, omsg};
+//! use omsg::testing::capture_logs;
+//!
+//! let logs = capture_logs(|| {
+//!     omsg!("deposited {} into reserve {}", 42u64, 3u8);
+//! });
+//! assert_logged!(logs, "deposited {} into reserve {}", 42, 3);
+//! assert_log_contains!(logs, "reserve 3");
+//! ```
+//!
+//! the stubs are installed once per process. besides `sol_log` they forward the syscalls
+//! which solana-program has stubbed since 1.8, the oldest release omsg supports: cross program
+//! invocations, the sysvar getters, the `sol_mem*` functions and `sol_log_compute_units`. stubs
+//! installed before the first call to [capture_logs], such as those of `solana-program-test`,
+//! keep working for those syscalls. syscalls added to solana-program later, such as return data
+//! and `sol_log_data`, use the default stubs of solana-program once the capture stubs are
+//! installed
+
+use solana_program::account_info::AccountInfo;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::instruction::Instruction;
+use solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use std::cell::RefCell;
+use std::sync::{Once, OnceLock};
+
+thread_local! {
+    static CAPTURED: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+static INSTALL: Once = Once::new();
+static PREVIOUS: OnceLock<Box<dyn SyscallStubs>> = OnceLock::new();
+
+/// runs `f` and returns every line it logged through `sol_log`, in order
+pub fn capture_logs<F: FnOnce()>(f: F) -> Vec<String> {
+    INSTALL.call_once(|| {
+        let previous = set_syscall_stubs(Box::new(CaptureStubs));
+        let _ = PREVIOUS.set(previous);
+    });
+    // restores an enclosing capture, even if `f` panics
+    struct Restore(Option<Vec<String>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CAPTURED.with(|captured| *captured.borrow_mut() = self.0.take());
+        }
+    }
+    let restore = Restore(CAPTURED.with(|captured| captured.borrow_mut().replace(Vec::new())));
+    f();
+    let logs = CAPTURED.with(|captured| captured.borrow_mut().take());
+    drop(restore);
+    logs.unwrap_or_default()
+}
+
+/// asserts that a line equal to the formatted message was logged
+#[macro_export]
+macro_rules! assert_logged {
+    ($logs:expr, $($expected:tt)+) => {{
+        let expected = ::std::format!($($expected)+);
+        let logs: &[::std::string::String] = &$logs;
+        assert!(
+            logs.iter().any(|line| *line == expected),
+            "expected {:?} to be logged, logs were {:#?}",
+            expected,
+            logs,
+        );
+    }};
+}
+
+/// asserts that a line containing the formatted text was logged
+#[macro_export]
+macro_rules! assert_log_contains {
+    ($logs:expr, $($expected:tt)+) => {{
+        let expected = ::std::format!($($expected)+);
+        let logs: &[::std::string::String] = &$logs;
+        assert!(
+            logs.iter().any(|line| line.contains(expected.as_str())),
+            "expected a log containing {:?}, logs were {:#?}",
+            expected,
+            logs,
+        );
+    }};
+}
+
+/// the stubs used until the previously installed stubs are known
+struct Fallback;
+
+impl SyscallStubs for Fallback {}
+
+fn previous() -> &'static dyn SyscallStubs {
+    match PREVIOUS.get() {
+        Some(previous) => previous.as_ref(),
+        None => &Fallback,
+    }
+}
+
+/// records `sol_log` lines of capturing threads, and forwards the lines of other threads and
+/// the other syscalls to the previous stubs
+struct CaptureStubs;
+
+impl SyscallStubs for CaptureStubs {
+    fn sol_log(&self, message: &str) {
+        let captured = CAPTURED.with(|captured| match captured.borrow_mut().as_mut() {
+            Some(logs) => {
+                logs.push(message.to_string());
+                true
+            }
+            None => false,
+        });
+        if !captured {
+            previous().sol_log(message)
+        }
+    }
+
+    fn sol_log_compute_units(&self) {
+        previous().sol_log_compute_units()
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        previous().sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        previous().sol_get_clock_sysvar(var_addr)
+    }
+
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        previous().sol_get_epoch_schedule_sysvar(var_addr)
+    }
+
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        previous().sol_get_fees_sysvar(var_addr)
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        previous().sol_get_rent_sysvar(var_addr)
+    }
+
+    unsafe fn sol_memcpy(&self, dst: *mut u8, src: *const u8, n: usize) {
+        previous().sol_memcpy(dst, src, n)
+    }
+
+    unsafe fn sol_memmove(&self, dst: *mut u8, src: *const u8, n: usize) {
+        previous().sol_memmove(dst, src, n)
+    }
+
+    unsafe fn sol_memcmp(&self, s1: *const u8, s2: *const u8, n: usize, result: *mut i32) {
+        previous().sol_memcmp(s1, s2, n, result)
+    }
+
+    unsafe fn sol_memset(&self, s: *mut u8, c: u8, n: usize) {
+        previous().sol_memset(s, c, n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_capture_logs() {
+        let logs = capture_logs(|| {
+            crate::omsg!("first {}", 1u8);
+            solana_program::msg!("second");
+        });
+        assert_eq!(logs, vec!["first 1".to_string(), "second".to_string()]);
+        // nothing is captured outside of `capture_logs`
+        crate::omsg!("not captured");
+        assert!(capture_logs(|| {}).is_empty());
+    }
+
+    #[test]
+    fn test_nested_capture_logs() {
+        let mut inner = Vec::new();
+        let outer = capture_logs(|| {
+            crate::omsg!("outer");
+            inner = capture_logs(|| {
+                crate::omsg!("inner");
+            });
+            crate::omsg!("outer again");
+        });
+        assert_eq!(inner, vec!["inner".to_string()]);
+        assert_eq!(outer, vec!["outer".to_string(), "outer again".to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected \"missing\" to be logged")]
+    fn test_assert_logged_failure() {
+        let logs = capture_logs(|| {
+            crate::omsg!("present");
+        });
+        assert_logged!(logs, "missing");
+    }
+}
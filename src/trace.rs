@@ -0,0 +1,114 @@
+//! compile time construction of the `[file.rs:123] ` prefixes used by the `omsg_trace!` family
+//! of macros. the prefix is assembled from `file!()`, `line!()` and friends into a single
+//! `&'static str` during constant evaluation, so tracing costs nothing beyond the prefix bytes.
+
+/// returns the file name component of the path returned by `file!()`
+pub const fn file_name(path: &str) -> &str {
+    let bytes = path.as_bytes();
+    let mut start = bytes.len();
+    while start > 0 && bytes[start - 1] != b'/' && bytes[start - 1] != b'\\' {
+        start -= 1;
+    }
+    let (_, name) = bytes.split_at(start);
+    as_str(name)
+}
+
+/// returns the combined length of `parts`
+#[doc(hidden)]
+pub const fn concat_len(parts: &[&str]) -> usize {
+    let mut len = 0;
+    let mut i = 0;
+    while i < parts.len() {
+        len += parts[i].len();
+        i += 1;
+    }
+    len
+}
+
+/// concatenates `parts` into an array, `LEN` must be the result of [concat_len]
+#[doc(hidden)]
+pub const fn concat_bytes<const LEN: usize>(parts: &[&str]) -> [u8; LEN] {
+    let mut out = [0u8; LEN];
+    let mut used = 0;
+    let mut i = 0;
+    while i < parts.len() {
+        let part = parts[i].as_bytes();
+        let mut j = 0;
+        while j < part.len() {
+            out[used] = part[j];
+            used += 1;
+            j += 1;
+        }
+        i += 1;
+    }
+    assert!(used == LEN, "LEN must be the combined length of the parts");
+    out
+}
+
+/// converts bytes produced by [concat_bytes] back into a `str`
+#[doc(hidden)]
+pub const fn as_str(bytes: &[u8]) -> &str {
+    match core::str::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(_) => panic!("concatenated str parts are always valid utf8"),
+    }
+}
+
+/// concatenates string constants into a single `&'static str` at compile time
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __const_concat {
+    ($($part:expr),* $(,)?) => {{
+        const PARTS: &[&str] = &[$($part),*];
+        const LEN: usize = $crate::trace::concat_len(PARTS);
+        const BYTES: [u8; LEN] = $crate::trace::concat_bytes::<LEN>(PARTS);
+        const CONCATENATED: &str = $crate::trace::as_str(&BYTES);
+        CONCATENATED
+    }};
+}
+
+/// expands to the `&'static str` prefix of a trace message for the call site
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __trace_prefix {
+    (file) => {
+        $crate::__const_concat!(
+            "[",
+            $crate::trace::file_name(::core::file!()),
+            ::core::concat!(":", ::core::line!(), "] "),
+        )
+    };
+    (column) => {
+        $crate::__const_concat!(
+            "[",
+            $crate::trace::file_name(::core::file!()),
+            ::core::concat!(":", ::core::line!(), ":", ::core::column!(), "] "),
+        )
+    };
+    (module) => {
+        ::core::concat!("[", ::core::module_path!(), ":", ::core::line!(), "] ")
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("src/processor.rs"), "processor.rs");
+        assert_eq!(file_name("programs\\lending\\src\\lib.rs"), "lib.rs");
+        assert_eq!(file_name("lib.rs"), "lib.rs");
+        assert_eq!(file_name(""), "");
+    }
+
+    #[test]
+    fn test_trace_prefixes() {
+        let (prefix, line) = (__trace_prefix!(file), line!());
+        assert_eq!(prefix, format!("[trace.rs:{}] ", line));
+        let (prefix, line) = (__trace_prefix!(module), line!());
+        assert_eq!(prefix, format!("[omsg::trace::test:{}] ", line));
+        let (prefix, line) = (__trace_prefix!(column), line!());
+        assert_eq!(prefix, format!("[trace.rs:{}:31] ", line));
+    }
+}
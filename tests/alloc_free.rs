@@ -0,0 +1,62 @@
+//! verifies that the stack path of `omsg!` and `omsg_trace!` performs no heap allocations.
+//! a counting global allocator is installed for this test binary, and a no-op syscall stub
+//! replaces the default one (which prints through the captured, heap backed stdout)
+
+use omsg::{ojson, okv, omsg, omsg_fast, omsg_trace, omsg_truncate};
+use solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|a| a.set(a.get() + 1));
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+struct NoopStubs;
+
+impl SyscallStubs for NoopStubs {
+    fn sol_log(&self, _message: &str) {}
+}
+
+/// returns the number of heap allocations performed by the current thread while running `f`
+fn count_allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(|a| a.get());
+    f();
+    ALLOCATIONS.with(|a| a.get()) - before
+}
+
+#[test]
+fn test_stack_path_is_allocation_free() {
+    set_syscall_stubs(Box::new(NoopStubs));
+    // warm up any lazily initialized state before measuring
+    omsg!("warm up {}", 1u8);
+
+    let allocations = count_allocations(|| {
+        omsg!("abc too {}", "yooo");
+        omsg!("reserve {} refreshed", 3u8);
+        omsg_trace!("abc too {}", "yoooo");
+        let key = solana_program::pubkey::Pubkey::default();
+        omsg_fast!("reserve {} refreshed by {}", 3u8, key);
+        omsg!("reserve {} refreshed by {}", 3u8, omsg::encoding::OmsgPubkey::new(&key));
+        omsg!("reserve {} refreshed by {}", 3u8, omsg::encoding::OmsgPubkey::short(&key));
+        okv!("refresh", reserve = 3u8, user = key, memo = "needs quotes");
+        ojson!(quote_large_ints; event = "refresh", amount = u64::MAX, user = key);
+        // too long for every bucket, but truncated instead of formatted on the heap
+        omsg_truncate!("liquidating {:?}", [u64::MAX; 50]);
+    });
+    assert_eq!(allocations, 0);
+}
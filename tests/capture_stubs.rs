@@ -0,0 +1,53 @@
+//! verifies that `capture_logs` keeps forwarding syscalls other than `sol_log` to the stubs
+//! installed before it, as `solana-program-test` does for cross program invocations. the stubs
+//! are installed once per process, so this runs in its own test binary
+
+use omsg::omsg;
+use omsg::testing::capture_logs;
+use solana_program::account_info::AccountInfo;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::instruction::Instruction;
+use solana_program::program::invoke;
+use solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program::sysvar::Sysvar;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static INVOKES: AtomicUsize = AtomicUsize::new(0);
+static LOGS: AtomicUsize = AtomicUsize::new(0);
+
+/// stands in for the stubs of `solana-program-test`
+struct ProgramTestStubs;
+
+impl SyscallStubs for ProgramTestStubs {
+    fn sol_log(&self, _message: &str) {
+        LOGS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn sol_invoke_signed(&self, _instruction: &Instruction, _account_infos: &[AccountInfo], _signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+        INVOKES.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let rent = Rent { lamports_per_byte_year: 7, ..Rent::default() };
+        unsafe { *(var_addr as *mut Rent) = rent };
+        solana_program::entrypoint::SUCCESS
+    }
+}
+
+#[test]
+fn test_previous_stubs_still_reached() {
+    set_syscall_stubs(Box::new(ProgramTestStubs));
+    let logs = capture_logs(|| {
+        omsg!("captured");
+    });
+    assert_eq!(logs, vec!["captured"]);
+
+    invoke(&Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]), &[]).unwrap();
+    assert_eq!(INVOKES.load(Ordering::Relaxed), 1);
+    assert_eq!(Rent::get().unwrap().lamports_per_byte_year, 7);
+    omsg!("not captured");
+    assert_eq!(LOGS.load(Ordering::Relaxed), 1);
+}
@@ -0,0 +1,37 @@
+//! compares the native release binaries of the `code_size` examples, whose log sites use `omsg!`
+//! and the way `omsg!` used to be expanded. this is only a rough proxy for the code each log
+//! site emits: it doesn't build for the solana target, so it says nothing about the size of a
+//! deployed `.so` or about the stack frames of a program, which need `cargo build-sbf` to
+//! measure. building the examples in release mode is slow, so the test has to be requested
+//! explicitly, which the `code-size` job of CI does:
+//!
+//! `cargo test --test code_size -- --ignored --nocapture`
+
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+#[ignore = "builds the code size examples in release mode"]
+fn test_native_code_size() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let target_dir = manifest_dir.join("target").join("code-size");
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--release", "--example", "code_size", "--example", "code_size_legacy"])
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .current_dir(manifest_dir)
+        .status()
+        .expect("failed to run cargo");
+    assert!(status.success(), "building the code size examples failed");
+
+    let size = |name: &str| {
+        let path = target_dir.join("release").join("examples").join(name);
+        std::fs::metadata(path.with_extension(std::env::consts::EXE_EXTENSION))
+            .unwrap_or_else(|e| panic!("missing example {}: {}", name, e))
+            .len()
+    };
+    let legacy = size("code_size_legacy");
+    let current = size("code_size");
+    println!("native binaries: legacy expansion {} bytes, single entry point {} bytes", legacy, current);
+    assert!(current < legacy, "the native binary grew from {} to {} bytes", legacy, current);
+}
@@ -0,0 +1,219 @@
+//! every module in this crate imports nothing but the macro under test, and declares decoy
+//! items with the names the macros use internally. if any macro refers to an item by its bare
+//! name instead of going through `$crate` or `::core`, this crate fails to compile. omsg is a
+//! dependency of this crate under the name `omsg_renamed`, and `omsg` is one of the decoys, so
+//! macros referring to the crate by its own name fail to compile as well.
+//!
+//! this crate intentionally does not depend on `solana-program`.
+#![deny(unused_imports)]
+#![allow(unused_macros, dead_code)]
+
+// decoys for the macros used inside the omsg macros, textually in scope for every module below
+macro_rules! format_args {
+    ($($t:tt)*) => {
+        compile_error!("omsg used the call site `format_args!`")
+    };
+}
+macro_rules! stringify {
+    ($($t:tt)*) => {
+        compile_error!("omsg used the call site `stringify!`")
+    };
+}
+macro_rules! concat {
+    ($($t:tt)*) => {
+        compile_error!("omsg used the call site `concat!`")
+    };
+}
+macro_rules! file {
+    ($($t:tt)*) => {
+        compile_error!("omsg used the call site `file!`")
+    };
+}
+macro_rules! line {
+    ($($t:tt)*) => {
+        compile_error!("omsg used the call site `line!`")
+    };
+}
+
+/// decoys for the items used inside the omsg macros
+macro_rules! decoy_items {
+    () => {
+        struct ArrForm;
+        mod sizing {}
+        mod buckets {}
+        mod trace {}
+        mod omsg {}
+    };
+}
+
+pub mod only_arrform {
+    use omsg_renamed::arrform;
+    decoy_items!();
+
+    pub fn format(amount: u64) -> usize {
+        arrform!(64, "deposited {}", amount).as_str().len()
+    }
+}
+
+pub mod only_try_arrform {
+    use omsg_renamed::try_arrform;
+    decoy_items!();
+
+    pub fn format(amount: u64) -> Option<usize> {
+        try_arrform!(16, "deposited {}", amount).ok().map(|af| af.as_str().len())
+    }
+}
+
+pub mod only_sum {
+    use omsg_renamed::sum;
+    decoy_items!();
+
+    pub fn bound(amount: u64) -> usize {
+        sum!("deposited {}", amount)
+    }
+}
+
+// `sum!` and `arrform!` are themselves exported, so they can only be decoyed after their own checks
+macro_rules! sum {
+    ($($t:tt)*) => {
+        compile_error!("omsg used the call site `sum!`")
+    };
+}
+macro_rules! arrform {
+    ($($t:tt)*) => {
+        compile_error!("omsg used the call site `arrform!`")
+    };
+}
+macro_rules! msg {
+    ($($t:tt)*) => {
+        compile_error!("omsg used the call site `msg!`")
+    };
+}
+
+pub mod only_omsg {
+    use omsg_renamed::omsg;
+    decoy_items!();
+
+    pub fn log(amount: u64) {
+        omsg!("refreshed reserve");
+        omsg!("deposited {} into reserve {}", amount, 3u8);
+        omsg!("deposited {amount}");
+    }
+}
+
+pub mod only_omsg_truncate {
+    use omsg_renamed::omsg_truncate;
+    decoy_items!();
+
+    pub fn log(amount: u64) {
+        omsg_truncate!("refreshed reserve");
+        omsg_truncate!("deposited {} into reserve {}", amount, 3u8);
+    }
+}
+
+pub mod only_omsg_trace {
+    use omsg_renamed::omsg_trace;
+    decoy_items!();
+
+    pub fn log(amount: u64) {
+        omsg_trace!("refreshed reserve");
+        omsg_trace!("deposited {} into reserve {}", amount, 3u8);
+    }
+}
+
+pub mod only_omsg_trace_column {
+    use omsg_renamed::omsg_trace_column;
+    decoy_items!();
+
+    pub fn log(amount: u64) {
+        omsg_trace_column!("deposited {}", amount);
+    }
+}
+
+pub mod only_omsg_trace_module {
+    use omsg_renamed::omsg_trace_module;
+    decoy_items!();
+
+    pub fn log(amount: u64) {
+        omsg_trace_module!("deposited {}", amount);
+    }
+}
+
+pub mod only_write_and_flush {
+    use omsg_renamed::{omsg_flush, omsg_write};
+    decoy_items!();
+    mod __private {}
+
+    pub fn log(af: &mut omsg_renamed::ArrForm<64>, amount: u64) {
+        omsg_write!(af, "deposited {}", amount);
+        omsg_flush!(af);
+    }
+}
+
+pub mod only_level_macros {
+    use omsg_renamed::{omsg_debug, omsg_error, omsg_info, omsg_trace_level, omsg_warn};
+    decoy_items!();
+    mod level {}
+
+    pub fn log(amount: u64) {
+        omsg_error!("deposited {}", amount);
+        omsg_warn!("deposited {}", amount);
+        omsg_info!("deposited {}", amount);
+        omsg_debug!("deposited {}", amount);
+        omsg_trace_level!("deposited {}", amount);
+    }
+}
+
+pub mod only_omsg_fast {
+    use omsg_renamed::{oformat, omsg_fast};
+    decoy_items!();
+    mod fast {}
+
+    pub fn log(amount: u64) -> usize {
+        omsg_fast!("refreshed reserve");
+        omsg_fast!("deposited {} into reserve {}", amount, 3u8);
+        omsg_fast!("deposited {amount}");
+        oformat!(64, "deposited {}", amount).len()
+    }
+}
+
+pub mod only_okv {
+    use omsg_renamed::okv;
+    decoy_items!();
+    mod kv {}
+
+    pub fn log(amount: u64) -> usize {
+        okv!("deposit", amount = amount, reserve = 3u8).len
+    }
+}
+
+pub mod only_ojson {
+    use omsg_renamed::ojson;
+    decoy_items!();
+    mod json {}
+
+    pub fn log(amount: u64) -> usize {
+        ojson!(quote_large_ints; event = "deposit", amount = amount).len
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_macros_expand_with_single_import() {
+        crate::only_omsg::log(42);
+        crate::only_omsg_truncate::log(42);
+        crate::only_omsg_trace::log(42);
+        crate::only_omsg_trace_column::log(42);
+        crate::only_omsg_trace_module::log(42);
+        crate::only_level_macros::log(42);
+        crate::only_write_and_flush::log(&mut omsg_renamed::ArrForm::new(), 42);
+        assert_eq!(crate::only_arrform::format(42), 12);
+        assert_eq!(crate::only_try_arrform::format(42), Some(12));
+        assert_eq!(crate::only_try_arrform::format(u64::MAX), None);
+        assert_eq!(crate::only_sum::bound(42), 12 + 20);
+        assert_eq!(crate::only_omsg_fast::log(42), 12);
+        assert_eq!(crate::only_okv::log(42), "deposit amount=42 reserve=3".len());
+        assert_eq!(crate::only_ojson::log(42), r#"{"event":"deposit","amount":"42"}"#.len());
+    }
+}